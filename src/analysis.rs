@@ -0,0 +1,236 @@
+// This module contains the post-game analysis. Given a recorded game, every
+// one of our turns is replayed and the action actually taken is compared
+// against the recommendation of the solver, similar to a blunder check in
+// chess. The loss of a move is measured in units of `score_hand`.
+use core::fmt;
+
+use crate::*;
+
+/// The pile a card is drawn from at the beginning of a turn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pile {
+    Stock,
+    Discard,
+}
+
+/// A single turn of ours, as recorded during a game.
+#[derive(Clone, Debug)]
+pub struct Turn {
+    /// Our hand at the beginning of the turn, before drawing.
+    pub hand: Hand,
+    /// Top card of the discard pile at the beginning of the turn.
+    pub top: Option<Card>,
+    /// Other cards known not to be in the stock, e.g. the rest of the
+    /// discard pile and the cards laid out on the table.
+    pub seen: Vec<Card>,
+    /// The pile we drew from.
+    pub pile: Pile,
+    /// The card we drew. Equals `top` if we drew from the discard pile.
+    pub drawn: Card,
+    /// The card we discarded at the end of the turn.
+    pub discard: Card,
+}
+
+/// A recorded game, consisting of all of our turns in order.
+#[derive(Clone, Debug, Default)]
+pub struct GameRecord {
+    pub turns: Vec<Turn>,
+}
+
+/// The verdict of the solver for a single turn.
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    /// Index of the turn in the game record.
+    pub turn: usize,
+    pub pile: Pile,
+    pub drawn: Card,
+    pub discard: Card,
+    /// The pile the solver would have drawn from.
+    pub best_pile: Pile,
+    /// The card the solver would have discarded, given the card
+    /// that was actually drawn.
+    pub best_discard: Card,
+    /// Expected score lost by drawing from the wrong pile.
+    pub pile_loss: f64,
+    /// Score lost by discarding the wrong card.
+    pub discard_loss: f64,
+}
+
+impl Annotation {
+    /// Total evaluation loss of the move.
+    pub fn loss(&self) -> f64 {
+        self.pile_loss + self.discard_loss
+    }
+}
+
+/// An annotated game record.
+#[derive(Clone, Debug, Default)]
+pub struct Analysis {
+    pub annotations: Vec<Annotation>,
+}
+
+impl Analysis {
+    /// Sum of the losses over all turns.
+    pub fn total_loss(&self) -> f64 {
+        self.annotations.iter().map(|a| a.loss()).sum()
+    }
+
+    /// Returns the n moves with the highest loss, worst first.
+    /// Moves without any loss are never reported.
+    pub fn biggest_mistakes(&self, n: usize) -> Vec<&Annotation> {
+        let mut v: Vec<&Annotation> = self.annotations.iter().filter(|a| a.loss() > 0.0).collect();
+        v.sort_by(|a, b| b.loss().total_cmp(&a.loss()));
+        v.truncate(n);
+        v
+    }
+}
+
+/// Walks through every turn of the given record and annotates it.
+pub fn analyze_game(record: &GameRecord, params: &EvalParams) -> Result<Analysis, Error> {
+    let mut analysis = Analysis::default();
+    for (i, turn) in record.turns.iter().enumerate() {
+        analysis.annotations.push(analyze_turn(i, turn, params)?);
+    }
+    Ok(analysis)
+}
+
+fn analyze_turn(i: usize, t: &Turn, params: &EvalParams) -> Result<Annotation, Error> {
+    let d = Deck::from_known(&t.hand, &t.seen, t.top.as_slice())?;
+
+    // value of each pile, assuming the best discard afterwards
+    let stock = expected_stock_value(&t.hand, &d, params)?;
+    let discard = match t.top {
+        Some(c) => Some(draw_value(&t.hand, c, &d, params)?),
+        None => None,
+    };
+    let (best_pile, best_value) = match discard {
+        Some(v) if v > stock => (Pile::Discard, v),
+        _ => (Pile::Stock, stock),
+    };
+    let taken_value = match t.pile {
+        Pile::Stock => stock,
+        Pile::Discard => discard.ok_or(Error::CardNotContained(t.drawn))?,
+    };
+
+    // quality of the discard, given the card actually drawn
+    let full = with_card(&t.hand, t.drawn)?;
+    let (best_discard, best_score) = best_discard(&full, &d, params).unwrap();
+    let pos = full
+        .iter()
+        .position(|&c| c == t.discard)
+        .ok_or(Error::CardNotContained(t.discard))?;
+    let mut rest = full.clone();
+    rest.remove(pos);
+    let score = score_hand(&rest, &d, params);
+
+    Ok(Annotation {
+        turn: i,
+        pile: t.pile,
+        drawn: t.drawn,
+        discard: t.discard,
+        best_pile,
+        best_discard,
+        pile_loss: (best_value - taken_value).max(0.0),
+        discard_loss: (best_score - score).max(0.0),
+    })
+}
+
+fn with_card(h: &Hand, c: Card) -> Result<Hand, Error> {
+    let mut h = h.clone();
+    h.try_push(c).map_err(|_| Error::CapacityError(c))?;
+    h.sort_unstable();
+    Ok(h)
+}
+
+/// Score of the hand after drawing the given card and discarding optimally.
+fn draw_value(h: &Hand, c: Card, d: &Deck, params: &EvalParams) -> Result<f64, Error> {
+    let full = with_card(h, c)?;
+    Ok(best_discard(&full, d, params).unwrap().1)
+}
+
+/// Expected score of the hand after drawing from the stock and discarding
+/// optimally.
+fn expected_stock_value(h: &Hand, d: &Deck, params: &EvalParams) -> Result<f64, Error> {
+    let total = d.remaining();
+    let mut value = 0.0;
+    for (c, n) in d.iter().filter(|&(_, n)| n > 0) {
+        value += n as f64 / total as f64 * draw_value(h, c, d, params)?;
+    }
+    Ok(value)
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Turn {}: drew {} from {:?}",
+            self.turn + 1,
+            self.drawn,
+            self.pile
+        )?;
+        if self.pile_loss > 0.0 {
+            write!(f, " (better: {:?}, -{:.3})", self.best_pile, self.pile_loss)?;
+        }
+        write!(f, ", discarded {}", self.discard)?;
+        if self.discard_loss > 0.0 {
+            write!(
+                f,
+                " (better: {}, -{:.3})",
+                self.best_discard, self.discard_loss
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for a in self.annotations.iter() {
+            writeln!(f, "{}", a)?;
+        }
+        writeln!(f, "\nTotal loss: {:.3}", self.total_loss())?;
+        let mistakes = self.biggest_mistakes(3);
+        if !mistakes.is_empty() {
+            writeln!(f, "Biggest mistakes:")?;
+            for a in mistakes {
+                writeln!(f, "  {}", a)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn flags_bad_discard() {
+    let record = GameRecord {
+        turns: vec![Turn {
+            hand: Hand::parse("Ac 2c 3c 5h 5d 9s").unwrap(),
+            top: Some(Card::parse("Kd").unwrap()),
+            seen: vec![],
+            pile: Pile::Stock,
+            drawn: Card::parse("4c").unwrap(),
+            discard: Card::parse("3c").unwrap(),
+        }],
+    };
+    let a = analyze_game(&record, &EvalParams::default()).unwrap();
+    assert_eq!(a.annotations.len(), 1);
+    assert!(a.annotations[0].discard_loss > 0.0);
+    assert_eq!(a.biggest_mistakes(3).len(), 1);
+}
+
+#[test]
+fn best_move_has_no_loss() {
+    let record = GameRecord {
+        turns: vec![Turn {
+            hand: Hand::parse("Ac 2c 3c 5h 5d 9s").unwrap(),
+            top: Some(Card::parse("5c").unwrap()),
+            seen: vec![],
+            pile: Pile::Discard,
+            drawn: Card::parse("5c").unwrap(),
+            discard: Card::parse("9s").unwrap(),
+        }],
+    };
+    let a = analyze_game(&record, &EvalParams::default()).unwrap();
+    assert_eq!(a.annotations[0].best_pile, Pile::Discard);
+    assert_eq!(a.total_loss(), 0.0);
+}
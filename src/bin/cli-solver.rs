@@ -1,11 +1,159 @@
+use std::path::Path;
+use std::str::FromStr;
+
 use remi_solver::*;
 
+const USAGE: &str = "usage:
+    cli-solver                                  draw a random card
+    cli-solver analyze <hand>                   show the best decompositions
+    cli-solver meld <cards>                     check whether the cards form a meld
+    cli-solver open <hand>                      compare opening now with holding back
+    cli-solver odds <seen> <targets> [draws]    odds of drawing any of the targets
+    cli-solver simulate <csv> <games> [params]  play rounds between bots and export statistics
+        [--seed <n>] [--turns <n>]              seed of the first deal and turn limit per round
+    cli-solver tune <params> <log> [iterations] tune evaluation weights";
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(|s| s.as_str()) {
+        None => draw(),
+        Some("analyze") if args.len() == 2 => analyze(&args[1]),
+        Some("meld") if args.len() == 2 => meld(&args[1]),
+        Some("open") if args.len() == 2 => open(&args[1]),
+        Some("odds") if args.len() == 3 || args.len() == 4 => odds(&args[1..]),
+        Some("simulate") if args.len() >= 3 => simulate_cmd(&args[1..]),
+        Some("tune") if args.len() >= 3 => tune_cmd(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn draw() -> Result<(), Error> {
     let mut deck = Deck::new();
     println!("{:?}", deck);
 
+    println!("\nI'm drawing a random card: {:?}", deck.remove_random()?);
+    Ok(())
+}
+
+fn analyze(hand: &str) -> Result<(), Error> {
+    let h = Hand::parse_sorted(hand)?;
+    let d = Deck::from_known(&h, &[], &[])?;
+    let top = top_decompositions(&h, &d, &EvalParams::default(), 3);
+    for (i, decomp) in top.iter().enumerate() {
+        let diff = decomp.score - top[0].score;
+        println!(
+            "{}. {:>7.3} ({:+.3})  {}",
+            i + 1,
+            decomp.score,
+            diff,
+            decomp
+        );
+        for g in decomp.groups_of(GroupKind::Meld) {
+            if let Ok(kind) = validate_meld(g.cards.iter()) {
+                println!("       {}: {}", g.cards.display(), kind);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn meld(cards: &str) -> Result<(), Error> {
+    let h = Hand::parse(cards)?;
+    match validate_meld(h.iter()) {
+        Ok(kind) => println!("{}", kind),
+        Err(reason) => println!("not a meld: {}", reason),
+    }
+    Ok(())
+}
+
+fn open(hand: &str) -> Result<(), Error> {
+    let h = Hand::parse_sorted(hand)?;
+    let d = Deck::from_known(&h, &[], &[])?;
+    let rules = RuleSet::default();
+    // the start of a round against a single opponent
+    let round = RoundState {
+        turn: 0,
+        opponent_cards: vec![rules.deal_size],
+    };
+    let params = EvalParams::default();
+    match decide_opening(&h, &d, &params, &round, &rules, &SimConfig::default()) {
+        Some(decision) => println!("{}", decision),
+        None => println!("the hand isn't worth {} points", rules.opening_points),
+    }
+    Ok(())
+}
+
+fn odds(args: &[String]) -> Result<(), Error> {
+    let seen = parse_cards(&args[0])?;
+    let targets = parse_cards(&args[1])?;
+    let draws: u32 = match args.get(2) {
+        Some(n) => parse_arg(n, "a draw count")?,
+        None => 1,
+    };
+    let d = Deck::from_seen(&seen)?;
     println!(
-        "\nI'm drawing a random card: {:?}",
-        deck.remove_random().unwrap()
+        "cards left:    {} ({} jokers)",
+        d.remaining(),
+        d.jokers_left()
     );
+    println!("outs:          {}", d.outs(&targets));
+    println!(
+        "p(any drawn):  {:.1}% in {} draws",
+        100.0 * d.p_any_drawn(&targets, draws),
+        draws
+    );
+    println!("expected outs: {:.2}", d.expected_outs(&targets, draws));
+    Ok(())
+}
+
+fn simulate_cmd(args: &[String]) -> Result<(), Error> {
+    let mut cfg = SimulationConfig {
+        games: parse_arg(&args[1], "a game count")?,
+        ..SimulationConfig::default()
+    };
+    // one bot per parameter file, playing against the default weights if
+    // only one is given
+    let mut bots = Vec::new();
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        let mut value = || rest.next().map_or("", |s| s.as_str());
+        match arg.as_str() {
+            "--seed" => cfg.seed = parse_arg(value(), "a seed")?,
+            "--turns" => cfg.max_turns = parse_arg(value(), "a turn limit")?,
+            path => bots.push(EvalParams::from_file(path)?),
+        }
+    }
+    while bots.len() < MIN_PLAYERS {
+        bots.push(EvalParams::default());
+    }
+    let stats = simulate(&bots, &cfg)?;
+    write_csv(&stats, Path::new(&args[0]))?;
+    print!("{}", summarize(&cfg, &stats));
+    Ok(())
+}
+
+fn tune_cmd(args: &[String]) -> Result<(), Error> {
+    let mut cfg = TuneConfig::default();
+    if let Some(n) = args.get(2) {
+        cfg.iterations = parse_arg(n, "an iteration count")?;
+    }
+    let best = tune(&cfg, Path::new(&args[0]), Path::new(&args[1]))?;
+    print!("{}", best);
+    Ok(())
+}
+
+/// Parses a numeric command line argument.
+fn parse_arg<T: FromStr>(arg: &str, expected: &'static str) -> Result<T, Error> {
+    arg.parse().map_err(|_| Error::ParseError {
+        token: arg.to_string(),
+        offset: 0,
+        expected,
+    })
 }
@@ -1,11 +1,14 @@
 // This module contains types relating to playing cards, their values and suits.
 use core::fmt;
 use std::char;
-
-use substring::Substring;
+use std::str::FromStr;
 
 use crate::*;
 
+const EXPECTED_CARD: &str = "a card like 5c, 10h or X";
+const EXPECTED_VALUE: &str = "a card value (A, 2-10, T, J, Q, K)";
+const EXPECTED_SUIT: &str = "a suit (c, d, h, s)";
+
 pub const JOKER_CARD: Card = Card {
     n: Value(0),
     suit: Suit::Joker,
@@ -27,17 +30,45 @@ impl Value {
         Value(n)
     }
 
-    pub fn from_str(c: &str) -> Result<Value, Error> {
-        match c {
-            "X" => Ok(Value::new(0)),
-            "A" => Ok(Value::new(1)),
-            "J" => Ok(Value::new(11)),
-            "Q" => Ok(Value::new(12)),
-            "K" => Ok(Value::new(13)),
+    pub fn to_int(self) -> u32 {
+        self.0
+    }
+
+    /// Iterates over all values of regular cards, from Ace to King.
+    pub fn all() -> impl Iterator<Item = Value> {
+        (1..=MAX_CARD_VAL).map(Value)
+    }
+}
+
+impl FromStr for Value {
+    type Err = Error;
+    /// Parses a value case-insensitively. Besides the regular notation,
+    /// "T" is accepted for ten.
+    fn from_str(c: &str) -> Result<Value, Error> {
+        let n = match c {
+            "X" | "x" | "🃏" => 0,
+            "A" | "a" => 1,
+            "T" | "t" => 10,
+            "J" | "j" => 11,
+            "Q" | "q" => 12,
+            "K" | "k" => 13,
             num => match num.parse::<u32>() {
-                Err(_) => Err(Error::ParseError),
-                Ok(n) => Ok(Value::new(n)),
+                Ok(n) if (1..=MAX_CARD_VAL).contains(&n) => n,
+                _ => return Err(Error::parse(c, 0, EXPECTED_VALUE)),
             },
+        };
+        Ok(Value(n))
+    }
+}
+
+/// Formats the value the same way `Value::from_str` expects it.
+/// E.g. "A", "7", "10", "K". The alternate style only differs for
+/// the joker value.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 if f.alternate() => write!(f, "🃏"),
+            _ => fmt::Debug::fmt(self, f),
         }
     }
 }
@@ -89,11 +120,23 @@ impl Suit {
 
     pub fn from_char(c: char) -> Result<Suit, Error> {
         match c {
-            'c' => Ok(Suit::Clubs),
-            'd' => Ok(Suit::Diamonds),
-            'h' => Ok(Suit::Hearts),
-            's' => Ok(Suit::Spades),
-            _ => Err(Error::ParseError),
+            'c' | 'C' | '♣' => Ok(Suit::Clubs),
+            'd' | 'D' | '♦' => Ok(Suit::Diamonds),
+            'h' | 'H' | '♥' => Ok(Suit::Hearts),
+            's' | 'S' | '♠' => Ok(Suit::Spades),
+            'X' | 'x' | '🃏' => Ok(Suit::Joker),
+            _ => Err(Error::parse(&c.to_string(), 0, EXPECTED_SUIT)),
+        }
+    }
+
+    /// Position of the suit within `Suit::iter`, e.g. Clubs = 0, Joker = 4.
+    pub fn index(self) -> usize {
+        match self {
+            Suit::Clubs => 0,
+            Suit::Diamonds => 1,
+            Suit::Hearts => 2,
+            Suit::Spades => 3,
+            Suit::Joker => 4,
         }
     }
 
@@ -102,6 +145,11 @@ impl Suit {
         static SUITS: [Suit; 5] = [Clubs, Diamonds, Hearts, Spades, Joker];
         SUITS.iter()
     }
+
+    /// Iterates over the four regular suits, excluding the joker.
+    pub fn standard() -> std::slice::Iter<'static, Suit> {
+        Suit::iter().as_slice()[..4].iter()
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -114,59 +162,96 @@ impl Card {
     /// Creates a new Card that is the predecessor of the current card.
     pub fn prev(&self) -> Option<Card> {
         if self.n.0 <= 1 {
-            return None
+            return None;
         }
-        return Some(Card{n: Value::new(self.n.0 - 1), suit: self.suit});
+        Some(Card {
+            n: Value::new(self.n.0 - 1),
+            suit: self.suit,
+        })
     }
     /// Creates a new Card that is the predecessor of the current card.
     pub fn next(&self) -> Option<Card> {
         if self.n.0 >= MAX_CARD_VAL {
-            return None
+            return None;
         }
-        return Some(Card{n: Value::new(self.n.0 + 1), suit: self.suit});
+        Some(Card {
+            n: Value::new(self.n.0 + 1),
+            suit: self.suit,
+        })
     }
- 
+
     pub fn is_predecessor(&self, c: &Card) -> bool {
-        self.n.0 == (c.n.0 - 1)
+        self.n.0 + 1 == c.n.0
     }
     /// Converts a Card to its index in the sorted list of unique cards.
     /// E.g.: Ac = 0, Ad = 1, ..., J = 52
     pub fn to_index(&self) -> usize {
         match self.suit {
             Suit::Joker => UNIQUE_CARDS - 1,
-            _ => {
-                let idx: u32 = (self.n.0 - 1) * 4 + self.suit.to_int();
-                idx.try_into().unwrap()
-            }
+            _ => (self.n.0 as usize - 1) * 4 + self.suit.index(),
         }
     }
     /// Returns a Card generated from the given canonical index.
-    /// (The index lies between 0...52 incl.)
-    pub fn from_index(i: u32) -> Card {
-        let n = Value::new((i / 4) + 1);
-        let suit = Suit::from_int(i % 4).unwrap();
+    /// (The index lies between 0...52 incl.) Inverse of `to_index`.
+    pub fn from_index(i: usize) -> Card {
+        debug_assert!(i < UNIQUE_CARDS, "card index {} out of range", i);
+        if i == UNIQUE_CARDS - 1 {
+            return JOKER_CARD;
+        }
+        let n = Value::new((i / 4) as u32 + 1);
+        let suit = Suit::iter().as_slice()[i % 4];
         Card { n, suit }
     }
 
+    /// Iterates over all unique cards in the order of their index,
+    /// i.e. Ac, Ad, Ah, As, 2c, ..., Ks, X.
+    pub fn all() -> impl Iterator<Item = Card> {
+        (0..UNIQUE_CARDS).map(Card::from_index)
+    }
+
+    /// Parses a single card token, e.g. "5c", "10H", "td" or "X".
+    /// Never panics; errors name the token and what was expected.
     pub fn parse(s: &str) -> Result<Card, Error> {
-        if s == "X" {
+        Card::parse_at(s, 0)
+    }
+
+    /// Parses a card token that starts at the given byte offset of a
+    /// larger input. The offset is only used for error reporting.
+    pub(crate) fn parse_at(s: &str, offset: usize) -> Result<Card, Error> {
+        if matches!(s, "X" | "x" | "🃏") {
             return Ok(JOKER_CARD);
         }
-        let char_count = s.chars().count();
-        assert!(char_count <= 3);
+        let mut chars = s.chars();
+        let suit = match chars.next_back().map(Suit::from_char) {
+            None => return Err(Error::parse(s, offset, EXPECTED_CARD)),
+            Some(Ok(Suit::Joker)) | Some(Err(_)) => {
+                return Err(Error::parse(s, offset, EXPECTED_SUIT))
+            }
+            Some(Ok(suit)) => suit,
+        };
+        match chars.as_str() {
+            "" => Err(Error::parse(s, offset, EXPECTED_CARD)),
+            value => match value.parse::<Value>() {
+                Ok(Value(0)) | Err(_) => Err(Error::parse(s, offset, EXPECTED_VALUE)),
+                Ok(n) => Ok(Card { n, suit }),
+            },
+        }
+    }
 
-        let r_n = Value::from_str(s.substring(0, char_count - 1));
-        let r_suit = Suit::from_char(s.chars().nth(char_count - 1).unwrap());
-        match (r_n, r_suit) {
-            (Ok(n), Ok(suit)) => Ok(Card { n, suit }),
-            _ => Err(Error::ParseError),
+    /// Penalty points of the card when it's left in a hand at the end of
+    /// a round. Number cards count their value, Ace and face cards 10 and
+    /// jokers 20.
+    pub fn points(&self) -> u32 {
+        match self.n.0 {
+            0 => 20,
+            1 => 10,
+            n => n.min(10),
         }
     }
 
     /// Returns a randomly generated Card.
     pub fn random() -> Card {
-        let bound: u32 = UNIQUE_CARDS.try_into().unwrap();
-        Card::from_index(fastrand::u32(..bound))
+        Card::from_index(fastrand::usize(..UNIQUE_CARDS))
     }
 }
 
@@ -204,3 +289,120 @@ impl fmt::Debug for Suit {
         }
     }
 }
+
+impl FromStr for Suit {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Suit, Error> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Suit::from_char(c),
+            _ => Err(Error::parse(s, 0, EXPECTED_SUIT)),
+        }
+    }
+}
+
+/// Formats a suit as a single character, e.g. "c". The alternate style
+/// uses the Unicode suit symbols instead, e.g. "♣".
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match (*self, f.alternate()) {
+            (Suit::Clubs, false) => "c",
+            (Suit::Diamonds, false) => "d",
+            (Suit::Hearts, false) => "h",
+            (Suit::Spades, false) => "s",
+            (Suit::Joker, false) => "X",
+            (Suit::Clubs, true) => "♣",
+            (Suit::Diamonds, true) => "♦",
+            (Suit::Hearts, true) => "♥",
+            (Suit::Spades, true) => "♠",
+            (Suit::Joker, true) => "🃏",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl FromStr for Card {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Card, Error> {
+        Card::parse(s)
+    }
+}
+
+/// Formats a card in the notation accepted by `Card::parse`, e.g. "5c"
+/// or "X" for the joker. The alternate style (`{:#}`) prints "5♣" and "🃏".
+///
+/// It holds that `Card::parse(&c.to_string()) == Ok(c)` for every card.
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.suit {
+            Suit::Joker => fmt::Display::fmt(&self.suit, f),
+            _ if f.alternate() => write!(f, "{:#}{:#}", self.n, self.suit),
+            _ => write!(f, "{}{}", self.n, self.suit),
+        }
+    }
+}
+
+#[test]
+fn card_notation_round_trip() {
+    for c in Card::all() {
+        assert_eq!(c.to_string().parse::<Card>().unwrap(), c);
+        assert_eq!(format!("{:#}", c).parse::<Card>().unwrap(), c);
+        assert_eq!(c.n.to_string().parse::<Value>().unwrap(), c.n);
+        assert_eq!(format!("{:#}", c.suit).parse::<Suit>().unwrap(), c.suit);
+    }
+}
+
+#[test]
+fn card_notation_unicode() {
+    assert_eq!(format!("{:#}", Card::parse("10h").unwrap()), "10♥");
+    assert_eq!(format!("{:#}", JOKER_CARD), "🃏");
+    assert_eq!(Card::parse("5♣").unwrap(), Card::parse("5c").unwrap());
+    assert!(Card::parse("XX").is_err());
+    assert!(Card::parse("5X").is_err());
+}
+
+#[test]
+fn lenient_card_parsing() {
+    let ten = Card::parse("10h").unwrap();
+    assert_eq!(Card::parse("10H").unwrap(), ten);
+    assert_eq!(Card::parse("th").unwrap(), ten);
+    assert_eq!(Card::parse("Th").unwrap(), ten);
+    assert_eq!(Card::parse("kS").unwrap(), Card::parse("Ks").unwrap());
+    assert_eq!(Card::parse("x").unwrap(), JOKER_CARD);
+}
+
+#[test]
+fn card_parsing_never_panics() {
+    for s in [
+        "", "c", "14c", "0c", "10", "100c", "Kx", "5cc", "🃏🃏", "-1h", "KKKKh",
+    ] {
+        match Card::parse(s) {
+            Err(Error::ParseError { token, offset, .. }) => {
+                assert_eq!(token, s);
+                assert_eq!(offset, 0);
+            }
+            r => panic!("{:?} parsed to {:?}", s, r),
+        }
+    }
+}
+
+#[test]
+fn card_index_bijection() {
+    assert_eq!(Card::all().count(), UNIQUE_CARDS);
+    for (i, c) in Card::all().enumerate() {
+        assert_eq!(c.to_index(), i);
+        assert_eq!(Card::from_index(c.to_index()), c);
+    }
+    assert_eq!(Card::from_index(0), Card::parse("Ac").unwrap());
+    assert_eq!(Card::from_index(7), Card::parse("2s").unwrap());
+    assert_eq!(Card::from_index(UNIQUE_CARDS - 1), JOKER_CARD);
+
+    let regular = Value::all().count() * Suit::standard().count();
+    assert_eq!(regular + 1, UNIQUE_CARDS);
+}
+
+#[test]
+#[should_panic]
+fn card_index_out_of_range() {
+    Card::from_index(UNIQUE_CARDS);
+}
@@ -7,13 +7,48 @@ pub enum Error {
     /// Card of given type is not contained in the deck.
     CardNotContained(Card),
 
-    ParseError,
+    /// A token of a Card or Hand string couldn't be parsed. Contains the
+    /// offending token, its byte offset within the input and a description
+    /// of what was expected instead.
+    ParseError {
+        token: String,
+        offset: usize,
+        expected: &'static str,
+    },
 
     CapacityError(Card),
 
+    /// More copies of cards were given than exist in a full deck.
+    /// Contains every such card together with the number of copies seen.
+    Overdrawn(Vec<(Card, u32)>),
+
     GroupIndexError,
 
+    /// Reading or writing a file failed. Contains the description of
+    /// the underlying I/O error.
+    IoError(String),
+
     EnumOutOfBounds,
+
+    /// A move isn't allowed by the rules. Contains the reason.
+    IllegalMove(&'static str),
+
+    /// A round can't be dealt for the given number of players.
+    PlayerCount(usize),
+
+    /// Hands of the given size can't be dealt, as the first hand gets an
+    /// additional card.
+    DealSize(usize),
+}
+
+impl Error {
+    pub(crate) fn parse(token: &str, offset: usize, expected: &'static str) -> Error {
+        Error::ParseError {
+            token: token.to_string(),
+            offset,
+            expected,
+        }
+    }
 }
 
 impl ::std::error::Error for Error {}
@@ -22,10 +57,42 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::CardNotContained(c) => write!(f, "No cards of type {:?} left", c),
-            Error::ParseError => write!(f, "Couldn't parse Card or Hand string"),
+            Error::ParseError {
+                token,
+                offset,
+                expected,
+            } => write!(
+                f,
+                "Couldn't parse '{}' at byte {}, expected {}",
+                token, offset, expected
+            ),
+            Error::PlayerCount(n) => write!(
+                f,
+                "Can't deal for {} players, expected {} to {}",
+                n,
+                crate::MIN_PLAYERS,
+                crate::MAX_PLAYERS
+            ),
+            Error::DealSize(n) => write!(
+                f,
+                "Can't deal {} cards per hand, expected at most {}",
+                n,
+                crate::MAX_HAND_SIZE - 1
+            ),
+            Error::IllegalMove(reason) => write!(f, "Illegal move: {}", reason),
             Error::CapacityError(c) => write!(f, "Can't add card {:?}, hand is full", c),
+            Error::Overdrawn(cards) => {
+                write!(f, "More copies than contained in the deck:")?;
+                for (c, n) in cards {
+                    write!(f, " {}x {}", n, c)?;
+                }
+                Ok(())
+            }
             Error::GroupIndexError => write!(f, "Incorrect index given for group"),
-            Error::EnumOutOfBounds => write!(f, "Enum conversion value wasn't correct. Check definition!"),
+            Error::IoError(e) => write!(f, "I/O error: {}", e),
+            Error::EnumOutOfBounds => {
+                write!(f, "Enum conversion value wasn't correct. Check definition!")
+            }
         }
     }
 }
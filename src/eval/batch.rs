@@ -0,0 +1,94 @@
+use std::thread;
+
+use crate::gamedef::*;
+
+use super::*;
+
+/// Number of worker threads used by the batch functions.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Scores many hands against the same deck, spread over all available
+/// threads. Every thread uses its own `SearchCache`.
+pub fn score_hands(hands: &[Hand], d: &Deck, params: &EvalParams) -> Vec<f64> {
+    let threads = default_threads().min(hands.len()).max(1);
+    let chunk = hands.len().div_ceil(threads);
+    let mut scores = vec![0.0; hands.len()];
+    thread::scope(|s| {
+        for (hs, out) in hands
+            .chunks(chunk.max(1))
+            .zip(scores.chunks_mut(chunk.max(1)))
+        {
+            s.spawn(move || {
+                let mut cache = SearchCache::default();
+                for (h, score) in hs.iter().zip(out.iter_mut()) {
+                    *score = score_hand_cached(h, d, params, &mut cache);
+                }
+            });
+        }
+    });
+    scores
+}
+
+/// Runs `samples` Monte Carlo rollouts on the given number of threads and
+/// returns their results in order.
+///
+/// Every rollout gets its own random generator, seeded from `seed` and the
+/// index of the rollout, so the results don't depend on the thread count.
+pub fn rollouts<T, F>(samples: u32, seed: u64, threads: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&fastrand::Rng) -> T + Sync,
+{
+    let threads = threads.clamp(1, samples.max(1) as usize);
+    let chunk = (samples as usize).div_ceil(threads);
+    let f = &f;
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                let start = t * chunk;
+                let end = ((t + 1) * chunk).min(samples as usize);
+                s.spawn(move || {
+                    (start..end)
+                        .map(|i| f(&fastrand::Rng::with_seed(rollout_seed(seed, i as u64))))
+                        .collect::<Vec<T>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect()
+    })
+}
+
+/// Derives the seed of a single rollout (splitmix64).
+fn rollout_seed(seed: u64, i: u64) -> u64 {
+    let mut z = seed.wrapping_add(i.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[test]
+fn batch_matches_sequential() {
+    let params = EvalParams::default();
+    let d = Deck::new();
+    let hands: Vec<Hand> = ["Ac 2c 3c 9d", "5h 5d 5s Kc", "2s 4s 6s 8s", "X Qh Kh Ah"]
+        .iter()
+        .map(|s| Hand::parse_sorted(s).unwrap())
+        .collect();
+    let scores = score_hands(&hands, &d, &params);
+    for (h, s) in hands.iter().zip(scores) {
+        assert_eq!(score_hand(h, &d, &params), s);
+    }
+}
+
+#[test]
+fn rollouts_are_deterministic() {
+    let one = rollouts(50, 3, 1, |rng| rng.u32(..));
+    let four = rollouts(50, 3, 4, |rng| rng.u32(..));
+    assert_eq!(one.len(), 50);
+    assert_eq!(one, four);
+}
@@ -0,0 +1,83 @@
+use crate::card::*;
+use crate::gamedef::*;
+use crate::opponent::*;
+
+/// Danger of discarding the given card, between 0 and 1.
+///
+/// Combines the chance that the card can be laid off onto a meld on the
+/// table with the chance that it completes a meld in the hand of the next
+/// player, who is the only one allowed to pick it up.
+pub fn discard_danger(c: Card, table: &Table, next: &OpponentModel) -> f64 {
+    let layoff = match table.accepts(c) {
+        true => 1.0,
+        false => 0.0,
+    };
+    (1.0 - (1.0 - layoff) * (1.0 - completion_odds(c, next))).clamp(0.0, 1.0)
+}
+
+/// Probability that the opponent holds two cards that form a meld
+/// together with the given card.
+fn completion_odds(c: Card, opp: &OpponentModel) -> f64 {
+    // a joker completes any pair
+    if c.suit == Suit::Joker {
+        return 1.0;
+    }
+    let mut pairs: Vec<(Card, Card)> = Vec::new();
+
+    // number melds
+    let others: Vec<Card> = Suit::standard()
+        .filter(|&&s| s != c.suit)
+        .map(|&suit| Card { n: c.n, suit })
+        .collect();
+    for i in 0..others.len() {
+        for j in (i + 1)..others.len() {
+            pairs.push((others[i], others[j]));
+        }
+    }
+    // street melds
+    let prev = c.prev();
+    let next = c.next();
+    let streets = [
+        (prev.and_then(|p| p.prev()), prev),
+        (prev, next),
+        (next, next.and_then(|n| n.next())),
+    ];
+    for (a, b) in streets {
+        if let (Some(a), Some(b)) = (a, b) {
+            pairs.push((a, b));
+        }
+    }
+
+    let joker = opp.p_holds(JOKER_CARD);
+    let mut miss = 1.0;
+    for (a, b) in pairs {
+        let (pa, pb) = (opp.p_holds(a), opp.p_holds(b));
+        // either both cards, or one of them together with a joker
+        let p = pa * pb + (pa + pb) * joker * 0.5;
+        miss *= 1.0 - p.min(1.0);
+    }
+    1.0 - miss
+}
+
+#[test]
+fn layoff_is_dangerous() {
+    let table = Table {
+        melds: vec![Hand::parse("5c 6c 7c").unwrap()],
+    };
+    let opp = OpponentModel::new(&Deck::new(), 14, BehaviorModel::default());
+    let safe = discard_danger(Card::parse("Kd").unwrap(), &table, &opp);
+    let danger = discard_danger(Card::parse("8c").unwrap(), &table, &opp);
+    assert_eq!(danger, 1.0);
+    assert!(safe < danger);
+}
+
+#[test]
+fn feeding_pickups_is_dangerous() {
+    let table = Table::default();
+    let mut opp = OpponentModel::new(&Deck::new(), 14, BehaviorModel::default());
+    opp.observe_pickup(Card::parse("9h").unwrap());
+    opp.observe_pickup(Card::parse("10h").unwrap());
+    let jack = discard_danger(Card::parse("Jh").unwrap(), &table, &opp);
+    let other = discard_danger(Card::parse("Js").unwrap(), &table, &opp);
+    assert!(jack > other);
+}
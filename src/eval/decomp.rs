@@ -1,9 +1,11 @@
 use crate::card::*;
 use crate::gamedef::*;
+use crate::meld::*;
 use crate::Error;
 use crate::MAX_HAND_SIZE;
 
 use arrayvec::ArrayVec;
+use core::fmt;
 
 /// Maximum number of cardgroups (hand) in a decomposition.
 pub const MAX_DECOMP_COUNT: usize = 15;
@@ -21,6 +23,18 @@ pub type Partition<'a> = ArrayVec<Group<'a>, MAX_DECOMP_COUNT>;
 /// melds or quasi-melds.
 pub type Group<'a> = ArrayVec<&'a Card, MAX_HAND_SIZE>;
 
+/// Type of a quasi-meld, i.e. a pair of cards that is one card short
+/// of a meld.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QuasiKind {
+    /// Two consecutive cards of the same suit, e.g. 5c 6c.
+    Street,
+    /// Two cards of the same suit with a gap, e.g. 5c 7c.
+    Gap,
+    /// Two cards of the same number, e.g. 5c 5h.
+    Set,
+}
+
 pub trait GroupCharacteristics<'a> {
     /// Creates a group from elements contained in the indices
     fn from_hand<'b>(h: &'b Hand, indices: &[usize]) -> Result<Group<'b>, Error>;
@@ -28,12 +42,13 @@ pub trait GroupCharacteristics<'a> {
     fn is_same_number(&self) -> bool;
     /// Returns true if Group has only one element.
     fn is_single(&self) -> bool;
-    /// Returns true if Group has at least 3 connected
-    /// components = meld.
+    /// Returns true if Group is a valid meld, see `validate_meld`.
     fn is_meld(&self) -> bool;
-    /// Returns true if Group has exactly 2 connected
-    /// components = quasi-meld.
+    /// Returns true if Group is a quasi-meld, see `quasi_kind`.
     fn is_quasi_meld(&self) -> bool;
+    /// Returns the type of quasi-meld, if the Group is a pair of cards
+    /// that can be completed to a meld with a single card.
+    fn quasi_kind(&self) -> Option<QuasiKind>;
 }
 
 impl<'a> GroupCharacteristics<'_> for Group<'a> {
@@ -60,43 +75,31 @@ impl<'a> GroupCharacteristics<'_> for Group<'a> {
         self.len() == 1
     }
     fn is_quasi_meld(&self) -> bool {
-        if self.len() != 2 {
-            return false;
-        }
-        self[0].is_predecessor(self[1])
+        self.quasi_kind().is_some()
     }
-    fn is_meld(&self) -> bool {
-        if self.len() < 3 {
-            return false;
+    fn quasi_kind(&self) -> Option<QuasiKind> {
+        if self.len() != 2 || self[0].suit == Suit::Joker || self[1].suit == Suit::Joker {
+            return None;
         }
-        // number meld
         if self[0].n == self[1].n {
-            return match self.len() {
-                3 => {
-                    return self[0].suit != self[1].suit
-                        && self[1].suit != self[2].suit
-                        && self[2].suit != self[0].suit
-                }
-                4 => {
-                    return self[0].suit != self[1].suit
-                        && self[1].suit != self[2].suit
-                        && self[2].suit != self[0].suit
-                        && self[2].suit != self[3].suit
-                        && self[1].suit != self[3].suit
-                        && self[0].suit != self[3].suit
-                }
-                _ => return false,
+            return match self[0].suit != self[1].suit {
+                true => Some(QuasiKind::Set),
+                false => None,
             };
         }
-        // suit meld
-        else {
-            let mut result: bool = true;
-            for i in 0..(self.len() - 1) {
-                result = result && self[i].is_predecessor(self[i + 1]);
-            }
-            return result;
+        if self[0].suit != self[1].suit {
+            return None;
+        }
+        let (a, b) = (self[0].n.to_int(), self[1].n.to_int());
+        match a.max(b) - a.min(b) {
+            1 => Some(QuasiKind::Street),
+            2 => Some(QuasiKind::Gap),
+            _ => None,
         }
     }
+    fn is_meld(&self) -> bool {
+        validate_meld(self.iter().copied()).is_ok()
+    }
 }
 
 /// Creates a partition from a given 2d-array of indices.
@@ -124,43 +127,121 @@ pub fn partition_suit(h: &Hand) -> Partition {
 }
 
 /// A naive decomposition is a simple, suboptimal partition that
-/// groups cards into melds or quasi-melds.
+/// groups cards into streets of the same suit, and then places the jokers
+/// greedily, see `place_jokers`.
 ///
 /// It can be used to bootstrap an optimal decomposition search.
 pub fn naive_decomposition(h: &Hand) -> Partition {
-    let p = partition_suit(h);
+    let mut p = street_groups(h);
+    place_jokers(&mut p, h);
+    p
+}
+
+/// Finds the optimal partition w.r.t. decomp score (see README).
+/// Compute-intensive method, so use sparingly.
+pub fn optimal_decomposition(h: &Hand) -> Partition<'_> {
+    let mut p = street_groups(h);
+
+    merge_single_numbers(&mut p);
+    place_jokers(&mut p, h);
+
+    p
+}
+
+/// Groups the regular cards of a sorted hand into maximal chains of
+/// consecutive cards of the same suit. Jokers are left out.
+fn street_groups(h: &Hand) -> Partition<'_> {
     let mut decomp = Partition::new();
-    for sg in p.iter() {
+    for sg in partition_suit(h)
+        .iter()
+        .filter(|g| g[0].suit != Suit::Joker)
+    {
         let mut current_group = Group::new();
         for &c in sg.iter() {
             match current_group.last() {
-                None => current_group.try_push(&c).unwrap(),
-                Some(&card) => {
-                    if card.is_predecessor(c) {
-                        current_group.try_push(&c).unwrap()
-                    } else {
-                        decomp.try_push(current_group.clone());
-                        current_group = Group::new();
-                        current_group.try_push(&c).unwrap();
-                    }
+                Some(&card) if card.suit != c.suit || !card.is_predecessor(c) => {
+                    decomp.push(current_group);
+                    current_group = Group::new();
                 }
-            };
+                _ => {}
+            }
+            current_group.push(c);
         }
         if !current_group.is_empty() {
-            decomp.try_push(current_group);
+            decomp.push(current_group);
         }
     }
     decomp
 }
 
-/// Finds the optimal partition w.r.t. decomp score (see README).
-/// Compute-intensive method, so use sparingly.
-pub fn optimal_decomposition(h: &Hand) -> Partition {
-    let mut p = naive_decomposition(h);
-
-    merge_single_numbers(&mut p);
+/// Adds the jokers of the hand to the partition, one at a time. Every
+/// joker goes where it puts the most cards into melds: it may extend a
+/// meld, complete a quasi-meld, or bridge two groups into one meld, such
+/// as [5c] and [7c]. Jokers that fit nowhere become singles.
+fn place_jokers<'a>(p: &mut Partition<'a>, h: &'a Hand) {
+    for joker in h.iter().filter(|c| c.suit == Suit::Joker) {
+        // (gain, merged groups, arranged meld)
+        let mut best: Option<(usize, (usize, Option<usize>), Group<'a>)> = None;
+        for i in 0..p.len() {
+            for j in (i..p.len()).map(Some).chain(Some(None)) {
+                if j == Some(i) {
+                    continue;
+                }
+                let mut g = p[i].clone();
+                g.push(joker);
+                if let Some(j) = j {
+                    g.extend(p[j].iter().copied());
+                }
+                let kind = match validate_meld(g.iter().copied()) {
+                    Ok(kind) => kind,
+                    Err(_) => continue,
+                };
+                let gain = 1 + [Some(i), j]
+                    .iter()
+                    .flatten()
+                    .filter(|&&k| !p[k].is_meld())
+                    .map(|&k| p[k].len())
+                    .sum::<usize>();
+                if best.as_ref().is_none_or(|b| gain > b.0) {
+                    best = Some((gain, (i, j), arrange(&g, &kind)));
+                }
+            }
+        }
+        match best {
+            Some((_, (i, j), g)) => {
+                p[i] = g;
+                if let Some(j) = j {
+                    p.remove(j);
+                }
+            }
+            None => {
+                let mut single = Group::new();
+                single.push(joker);
+                p.push(single);
+            }
+        }
+    }
+}
 
-    p
+/// Orders the cards of a meld, so that jokers of a run take the place of
+/// the numbers they stand in for.
+fn arrange<'a>(g: &Group<'a>, kind: &MeldKind) -> Group<'a> {
+    let (real, jokers): (Group, Group) = g.iter().copied().partition(|c| c.suit != Suit::Joker);
+    let mut real = real;
+    real.sort_unstable();
+    match kind {
+        MeldKind::Set { .. } => real.into_iter().chain(jokers).collect(),
+        MeldKind::Run { lo, hi, .. } => {
+            let mut jokers = jokers.into_iter();
+            let mut real = real.into_iter().peekable();
+            (lo.to_int()..=hi.to_int())
+                .filter_map(|n| match real.peek() {
+                    Some(c) if c.n == n => real.next(),
+                    _ => jokers.next(),
+                })
+                .collect()
+        }
+    }
 }
 
 /// Merges together all single(!) groups with the same number
@@ -186,6 +267,64 @@ fn merge_single_numbers(p: &mut Partition) {
     p.retain(|e| !e.is_empty());
 }
 
+/// Formats a partition with each group in brackets, e.g. "[Ac 2c 3c] [5h]".
+/// The alternate style uses Unicode suit symbols.
+pub struct DisplayPartition<'a, 'b>(pub &'b Partition<'a>);
+
+impl fmt::Display for DisplayPartition<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, g) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "[")?;
+            for (j, &c) in g.iter().enumerate() {
+                if j > 0 {
+                    write!(f, " ")?;
+                }
+                fmt::Display::fmt(c, f)?;
+            }
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a partition in the format of `DisplayPartition`. As groups
+/// borrow their cards, every parsed card is matched against a distinct,
+/// yet unused card of the given hand.
+///
+/// It holds that `parse_partition(h, &DisplayPartition(&p).to_string())`
+/// reproduces `p` for every partition `p` of `h`.
+pub fn parse_partition<'a>(h: &'a Hand, s: &str) -> Result<Partition<'a>, Error> {
+    let mut used = [false; MAX_HAND_SIZE];
+    let mut p = Partition::new();
+    let mut offset = s.len() - s.trim_start().len();
+    while offset < s.len() {
+        let rest = &s[offset..];
+        let inner = rest
+            .strip_prefix('[')
+            .ok_or_else(|| Error::parse(rest, offset, "a group starting with '['"))?;
+        let end = inner
+            .find(']')
+            .ok_or_else(|| Error::parse(rest, offset, "a group ending with ']'"))?;
+        let mut g = Group::new();
+        for (i, token) in tokenize(&inner[..end]) {
+            let card = Card::parse_at(token, offset + 1 + i)?;
+            let i = (0..h.len())
+                .find(|&i| !used[i] && h[i] == card)
+                .ok_or(Error::CardNotContained(card))?;
+            used[i] = true;
+            g.push(&h[i]);
+        }
+        p.try_push(g)
+            .map_err(|_| Error::parse(rest, offset, "at most 15 groups"))?;
+        offset += 1 + end + 1;
+        offset = s.len() - s[offset..].trim_start().len();
+    }
+    Ok(p)
+}
+
 pub fn partition_eq(p: &Partition, format: &'static str) {
     let f = format!("{:?}", p);
     assert_eq!(f, format);
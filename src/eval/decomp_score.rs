@@ -1,55 +1,77 @@
-use crate::MAX_CARD_VAL;
 use crate::card::*;
 use crate::gamedef::*;
+use crate::meld::*;
 use crate::Error;
+use crate::MAX_CARD_VAL;
 
 use super::decomp::*;
+use super::params::*;
 
 /// Relative score function to evaluate strength of decompositions.
 /// A decomp with a higher score should have a higher chance to win
 /// Depends on remaining cards in the deck.
-pub fn decomp_score(p: &Partition, d: &Deck) -> f64 {
+pub fn decomp_score(p: &Partition, d: &Deck, params: &EvalParams) -> f64 {
     let mut score: f64 = 0.0;
     for g in p.iter() {
-        if !g.is_meld() {
-            score += 0.05 * g.len() as f64;
-        } else {
-            score += g.len() as f64;
-            score += extension_score(g, d);
+        match validate_meld(g.iter().copied()) {
+            Ok(kind) => {
+                score += params.meld_card * g.len() as f64;
+                score += params.long_meld * g.len().saturating_sub(3) as f64;
+                score += params.extension * extension_score(g, &kind, d);
+            }
+            Err(_) => {
+                score += quasi_meld_weight(g, params) * g.len() as f64;
+                let points: u32 = g.iter().map(|c| c.points()).sum();
+                score -= params.point_penalty * points as f64;
+            }
         }
+        let jokers = g.iter().filter(|c| c.suit == Suit::Joker).count();
+        score += params.joker * jokers as f64;
     }
 
     score
 }
 
-/// Likelihood of drawing cards within a turn horizon that extend the
-/// given meld.
-fn extension_score(g: &Group, d: &Deck) -> f64 {
-    if !g.is_meld() {
-        return 0.0;
+/// Value per card of a group that isn't a meld.
+fn quasi_meld_weight(g: &Group, params: &EvalParams) -> f64 {
+    match g.quasi_kind() {
+        Some(QuasiKind::Street) => params.quasi_street,
+        Some(QuasiKind::Gap) => params.quasi_gap,
+        Some(QuasiKind::Set) => params.quasi_set,
+        None => params.single,
     }
-    
-    // number meld
-    if g.is_same_number() {
-        // can't extend ace meld
-        if g.len() == 4 {
-            return 0.0;
-        }
-        let suit = remaining_suit(g).unwrap();
-        return d.odds_to_draw(Card { n: g[0].n, suit });
-    } 
-    // street meld
-    else {
-        let mut score = 0.0;
-        let suit = g[0].suit;
+}
 
-        if g[0].n != 1 {
-            score += d.odds_to_draw(g[0].prev().unwrap())
-        }
-        if g[g.len() - 1].n != MAX_CARD_VAL {
-            score += d.odds_to_draw(g[g.len() - 1].next().unwrap())
+/// Likelihood of drawing cards within a turn horizon that extend the
+/// given meld.
+fn extension_score(g: &Group, kind: &MeldKind, d: &Deck) -> f64 {
+    match *kind {
+        // can't extend a full set
+        MeldKind::Set { .. } if g.len() >= MAX_SET_SIZE => 0.0,
+        MeldKind::Set { n, jokers: 0 } => d.odds_to_draw(Card {
+            n,
+            suit: remaining_suit(g).unwrap(),
+        }),
+        MeldKind::Set { n, .. } => Suit::standard()
+            .filter(|&&suit| !g.iter().any(|c| c.suit == suit))
+            .map(|&suit| d.odds_to_draw(Card { n, suit }))
+            .sum(),
+        MeldKind::Run { suit, lo, hi, .. } => {
+            let mut score = 0.0;
+            if lo != 1 {
+                score += d.odds_to_draw(Card {
+                    n: Value::new(lo.to_int() - 1),
+                    suit,
+                });
+            }
+            if hi != MAX_CARD_VAL {
+                score += d.odds_to_draw(Card {
+                    n: Value::new(hi.to_int() + 1),
+                    suit,
+                });
+            }
+            score
         }
-        return score;
     }
 }
 
@@ -104,7 +126,6 @@ fn meld_extension_score_bounds() {
         &Deck::new(),
     );
     assert!(diff > 0.0);
-
 }
 
 /// Returns the signed score difference of the two given decompositions
@@ -113,8 +134,25 @@ fn cmp_decomp(h: &Hand, d1: &[&[usize]], d2: &[&[usize]], deck: &Deck) -> f64 {
     let p1 = partition_index(h, d1).unwrap();
     let p2 = partition_index(h, d2).unwrap();
 
-    let score1 = decomp_score(&p1, deck);
-    let score2 = decomp_score(&p2, deck);
+    let score1 = decomp_score(&p1, deck, &EvalParams::default());
+    let score2 = decomp_score(&p2, deck, &EvalParams::default());
 
     score1 - score2
 }
+
+#[test]
+fn quasi_meld_weights() {
+    let params = EvalParams {
+        quasi_street: 0.3,
+        quasi_gap: 0.2,
+        quasi_set: 0.1,
+        ..EvalParams::default()
+    };
+    let h = Hand::parse("5c 6c 7d 9d Kh Ks").unwrap();
+    let street = partition_index(&h, &[&[0, 1]]).unwrap();
+    let gap = partition_index(&h, &[&[2, 3]]).unwrap();
+    let set = partition_index(&h, &[&[4, 5]]).unwrap();
+    let d = Deck::new();
+    assert!(decomp_score(&street, &d, &params) > decomp_score(&gap, &d, &params));
+    assert!(decomp_score(&gap, &d, &params) > decomp_score(&set, &d, &params));
+}
@@ -1,5 +1,6 @@
 use super::decomp::*;
 use crate::card::*;
+use crate::eval::*;
 use crate::gamedef::*;
 
 /// Asserts whether the cards in a Group are equal to those
@@ -61,3 +62,113 @@ fn optimize_split_simple() {
     let mut p: Partition = optimal_decomposition(&h);
     // partition_eq(&p, "[[[Ac], [2c], [3c]], [[4c], [4d], [4h]]]");
 }
+
+#[test]
+fn display_round_trip() {
+    let h = Hand::parse_sorted("Ac 2c 3c 5h 5d X").unwrap();
+    assert_eq!(Hand::parse(&h.display().to_string()).unwrap(), h);
+    assert_eq!(Hand::parse(&format!("{:#}", h.display())).unwrap(), h);
+
+    let p = partition_index(&h, &[&[0, 1, 2], &[3, 4], &[5]]).unwrap();
+    let s = DisplayPartition(&p).to_string();
+    assert_eq!(s, "[Ac 2c 3c] [5d 5h] [X]");
+    let q = parse_partition(&h, &s).unwrap();
+    assert_eq!(format!("{:?}", q), format!("{:?}", p));
+    let q = parse_partition(&h, &format!("{:#}", DisplayPartition(&p))).unwrap();
+    assert_eq!(format!("{:?}", q), format!("{:?}", p));
+}
+
+#[test]
+fn lenient_hand_parsing() {
+    let h = Hand::parse("Ac 2c 3c").unwrap();
+    assert_eq!(Hand::parse("  ac,2C ,  3c  ").unwrap(), h);
+    assert_eq!(Hand::parse("").unwrap().len(), 0);
+
+    match Hand::parse("Ac  2c, 14c") {
+        Err(crate::Error::ParseError { token, offset, .. }) => {
+            assert_eq!(token, "14c");
+            assert_eq!(offset, 8);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+/// Builds the hand internally, which a borrowed Partition couldn't outlive.
+fn decompose(s: &str) -> Decomposition {
+    let h = Hand::parse_sorted(s).unwrap();
+    Decomposition::of(&h, &Deck::new(), &EvalParams::default())
+}
+
+#[test]
+fn owned_decomposition() {
+    let d = decompose("Ac 2c 3c 5s 5h 5c 9d 10d Kh");
+    assert_eq!(d.to_string(), "[Ac 2c 3c] [5c 5h 5s] [9d 10d] [Kh]");
+    assert_eq!(d.groups_of(GroupKind::Meld).count(), 2);
+    assert_eq!(d.groups[2].kind, GroupKind::QuasiMeld(QuasiKind::Street));
+    assert_eq!(d.groups[3].kind, GroupKind::Single);
+
+    let total: f64 = d.groups.iter().map(|g| g.score).sum();
+    assert_eq!(d.score, total);
+}
+
+#[test]
+fn owned_partition_round_trip() {
+    let h = Hand::parse_sorted("Ac 2c 3c 4c 4d 4h").unwrap();
+    let deck = Deck::new();
+    let params = EvalParams::default();
+    let p = partition_index(&h, &[&[0, 1, 2], &[3, 4, 5]]).unwrap();
+    let d = Decomposition::from_partition(&p, &deck, &params);
+    let q = d.to_partition();
+    assert_eq!(format!("{:?}", p), format!("{:?}", q));
+    assert_eq!(
+        d.score,
+        crate::eval::decomp_score::decomp_score(&p, &deck, &params)
+    );
+}
+
+#[test]
+fn naive_respects_suits() {
+    let h = Hand::parse_sorted("5c 6d 7c").unwrap();
+    let p = naive_decomposition(&h);
+    assert_eq!(DisplayPartition(&p).to_string(), "[5c] [7c] [6d]");
+}
+
+#[test]
+fn jokers_bridge_and_extend() {
+    let h = Hand::parse_sorted("5c 7c X").unwrap();
+    let p = naive_decomposition(&h);
+    assert_eq!(DisplayPartition(&p).to_string(), "[5c X 7c]");
+
+    // the joker completes the set rather than extending the street
+    let h = Hand::parse_sorted("Ac 2c 3c 9d 9h X").unwrap();
+    let p = optimal_decomposition(&h);
+    assert_eq!(DisplayPartition(&p).to_string(), "[Ac 2c 3c] [9d 9h X]");
+
+    // the second joker extends a meld
+    let h = Hand::parse_sorted("Jh Qh Kh X X 4s").unwrap();
+    let p = optimal_decomposition(&h);
+    assert_eq!(DisplayPartition(&p).to_string(), "[X X Jh Qh Kh] [4s]");
+    assert!(p[0].is_meld());
+}
+
+#[test]
+fn decomposition_of_unsorted_hand() {
+    let params = EvalParams::default();
+    let h = Hand::parse("7c 5c 6c Kd").unwrap();
+    let d = Decomposition::of(&h, &Deck::new(), &params);
+    assert_eq!(d.to_string(), "[5c 6c 7c] [Kd]");
+}
+
+#[test]
+fn quasi_melds_respect_suits() {
+    let quasi = |s: &str| {
+        let h = Hand::parse(s).unwrap();
+        let g: Group = h.iter().collect();
+        g.is_quasi_meld()
+    };
+    assert!(quasi("5c 6c"));
+    assert!(quasi("5c 7c"));
+    assert!(quasi("5c 5d"));
+    assert!(!quasi("5c 6d"));
+    assert!(!quasi("5c X"));
+}
@@ -0,0 +1,109 @@
+use core::fmt;
+
+use arrayvec::ArrayVec;
+
+use crate::card::*;
+use crate::gamedef::*;
+use crate::MAX_HAND_SIZE;
+
+use super::decomp::*;
+use super::decomp_score::*;
+use super::params::*;
+use super::sorted_hand;
+
+/// Classification of a group within a decomposition.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GroupKind {
+    Meld,
+    QuasiMeld(QuasiKind),
+    Single,
+}
+
+/// A group of a decomposition that owns its cards.
+#[derive(Clone, Debug)]
+pub struct OwnedGroup {
+    pub cards: ArrayVec<Card, MAX_HAND_SIZE>,
+    pub kind: GroupKind,
+    /// Contribution of the group to the `decomp_score`.
+    pub score: f64,
+}
+
+/// An owned decomposition, which doesn't borrow from a `Hand`. It can be
+/// stored in caches and returned from functions that build the hand
+/// internally. Groups that are neither meld nor quasi-meld are split up
+/// into singles, which doesn't change the score.
+#[derive(Clone, Debug)]
+pub struct Decomposition {
+    pub groups: ArrayVec<OwnedGroup, MAX_DECOMP_COUNT>,
+    /// Total `decomp_score` of the decomposition.
+    pub score: f64,
+}
+
+impl Decomposition {
+    /// Computes the optimal decomposition of the given hand, in any
+    /// order.
+    pub fn of(h: &Hand, d: &Deck, params: &EvalParams) -> Self {
+        let sorted = sorted_hand(h);
+        let p = optimal_decomposition(&sorted);
+        Decomposition::from_partition(&p, d, params)
+    }
+
+    /// Creates an owned copy of the partition and caches the kind and the
+    /// score of every group.
+    pub fn from_partition(p: &Partition, d: &Deck, params: &EvalParams) -> Self {
+        let mut groups = ArrayVec::new();
+        for g in p.iter() {
+            if g.is_meld() || g.quasi_kind().is_some() || g.len() <= 1 {
+                groups.push(owned_group(g, d, params));
+                continue;
+            }
+            for &c in g.iter() {
+                let mut single = Group::new();
+                single.push(c);
+                groups.push(owned_group(&single, d, params));
+            }
+        }
+        let score = groups.iter().map(|g: &OwnedGroup| g.score).sum();
+        Decomposition { groups, score }
+    }
+
+    /// Creates a borrowed partition whose groups point into this
+    /// decomposition.
+    pub fn to_partition(&self) -> Partition<'_> {
+        self.groups
+            .iter()
+            .map(|g| g.cards.iter().collect::<Group>())
+            .collect()
+    }
+
+    /// Iterates over all cards of the decomposition.
+    pub fn cards(&self) -> impl Iterator<Item = Card> + '_ {
+        self.groups.iter().flat_map(|g| g.cards.iter().copied())
+    }
+
+    /// Iterates over all groups of the given kind.
+    pub fn groups_of(&self, kind: GroupKind) -> impl Iterator<Item = &OwnedGroup> + '_ {
+        self.groups.iter().filter(move |g| g.kind == kind)
+    }
+}
+
+fn owned_group(g: &Group, d: &Deck, params: &EvalParams) -> OwnedGroup {
+    let kind = match (g.is_meld(), g.quasi_kind()) {
+        (true, _) => GroupKind::Meld,
+        (false, Some(q)) => GroupKind::QuasiMeld(q),
+        (false, None) => GroupKind::Single,
+    };
+    let mut single = Partition::new();
+    single.push(g.clone());
+    OwnedGroup {
+        cards: g.iter().map(|&&c| c).collect(),
+        kind,
+        score: decomp_score(&single, d, params),
+    }
+}
+
+impl fmt::Display for Decomposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&DisplayPartition(&self.to_partition()), f)
+    }
+}
@@ -0,0 +1,309 @@
+use crate::card::*;
+use crate::gamedef::*;
+use crate::hash::*;
+
+use super::*;
+
+/// Number of turns the exact endgame search looks ahead.
+pub const ENDGAME_TURNS: u32 = 3;
+
+/// Largest state size for which the exact endgame search is used instead
+/// of the heuristic evaluation, see `endgame_state_size`. The bound is
+/// loose, a search of this size takes well under 0.1s in a release build,
+/// which keeps a bot move interactive while covering the last few cards
+/// of a round with up to three turns to go.
+pub const ENDGAME_MAX_STATES: u64 = 50_000;
+
+/// Number of slots of the memo table of the exact endgame search.
+const ENDGAME_MEMO_SIZE: usize = 1 << 16;
+
+/// Upper bound on the number of positions the exact search visits for
+/// the given hand (including the drawn card), deck and number of turns.
+pub fn endgame_state_size(full: &Hand, d: &Deck, k: u32) -> u64 {
+    let draws = d.iter().filter(|&(_, n)| n > 0).count() as u64;
+    let discards = distinct(full).len() as u64;
+    (draws * discards)
+        .saturating_pow(k.saturating_sub(1))
+        .saturating_mul(discards)
+}
+
+/// Same as `endgame_state_size` for a hand before drawing. Every draw
+/// adds at most one distinct card to discard.
+pub fn endgame_draw_state_size(h: &Hand, d: &Deck, k: u32) -> u64 {
+    let draws = d.iter().filter(|&(_, n)| n > 0).count() as u64;
+    let discards = distinct(h).len() as u64 + 1;
+    (draws * discards)
+        .saturating_pow(k.saturating_sub(1))
+        .saturating_mul(draws)
+}
+
+/// Computes the exact probability of going out within `k` turns for
+/// every possible discard of the given hand, which includes the card drawn
+/// this turn. Only our own draws from the deck are considered. The result
+/// is sorted so that the optimal action comes first.
+pub fn endgame_discards(full: &Hand, d: &Deck, k: u32) -> Vec<(Card, f64)> {
+    endgame_plan(full, d, k).discards
+}
+
+/// Optimal discards for this and the following turns of the exact
+/// endgame search, see `endgame_plan`.
+pub struct EndgamePolicy {
+    /// The discards of the current turn with their probability of going
+    /// out within the remaining turns, optimal first.
+    pub discards: Vec<(Card, f64)>,
+    /// Turns left after the current discard.
+    pub turns: u32,
+    solver: Solver,
+}
+
+/// Same as `endgame_discards`, but keeps the search around, so that the
+/// discards of the following turns come from the same positions.
+pub fn endgame_plan(full: &Hand, d: &Deck, k: u32) -> EndgamePolicy {
+    let mut solver = Solver::default();
+    EndgamePolicy {
+        discards: solver.discards(full, d, k),
+        turns: k,
+        solver,
+    }
+}
+
+impl EndgamePolicy {
+    /// Advances to the next turn, given the hand including the card drawn
+    /// then and the deck after that draw.
+    pub fn next_turn(&mut self, full: &Hand, d: &Deck) {
+        self.turns = self.turns.saturating_sub(1);
+        self.discards = self.solver.discards(full, d, self.turns);
+    }
+
+    /// The optimal discard of the current turn.
+    pub fn best(&self) -> Option<Card> {
+        self.discards.first().map(|&(c, _)| c)
+    }
+}
+
+/// Exact probability of going out within `k` turns from a hand before
+/// drawing, when drawing from the deck every turn and discarding
+/// optimally.
+pub fn endgame_p_out(h: &Hand, d: &Deck, k: u32) -> f64 {
+    Solver::default().p_out(h, d, k)
+}
+
+/// Same as `endgame_p_out`, or `None` if `k` exceeds `ENDGAME_TURNS` or
+/// the state is too large for the exact search.
+pub fn endgame_p_out_bounded(h: &Hand, d: &Deck, k: u32) -> Option<f64> {
+    if k > ENDGAME_TURNS || endgame_draw_state_size(h, d, k) > ENDGAME_MAX_STATES {
+        return None;
+    }
+    Some(endgame_p_out(h, d, k))
+}
+
+/// Exact probability of going out within `k` turns for both piles the
+/// first card can be drawn from. The value of the discard pile is `None`
+/// if it's empty.
+pub fn endgame_piles(h: &Hand, top: Option<Card>, d: &Deck, k: u32) -> (f64, Option<f64>) {
+    let mut solver = Solver::default();
+    let stock = solver.p_out(h, d, k);
+    let discard = top.map(|c| {
+        let mut full = h.clone();
+        if full.try_push(c).is_err() {
+            return 0.0;
+        }
+        full.sort_unstable();
+        match can_go_out(&full) {
+            true => 1.0,
+            false => solver.best_discard(&full, d, k.saturating_sub(1)),
+        }
+    });
+    (stock, discard)
+}
+
+/// Chooses the discard for the given hand, which includes the card drawn
+/// this turn. Uses the exact endgame search if the state is small enough,
+/// and the penalty-aware `best_discard_in_round` otherwise or if going out
+/// within the search horizon is impossible.
+pub fn recommend_discard(
+    full: &Hand,
+    d: &Deck,
+    params: &EvalParams,
+    round: &RoundState,
+) -> Option<Card> {
+    if endgame_state_size(full, d, ENDGAME_TURNS) <= ENDGAME_MAX_STATES {
+        match endgame_discards(full, d, ENDGAME_TURNS).first() {
+            Some(&(c, p)) if p > 0.0 => return Some(c),
+            _ => {}
+        }
+    }
+    best_discard_in_round(full, d, params, round).map(|(c, _)| c)
+}
+
+/// Memoized expectimax search over the draws from the deck.
+struct Solver {
+    memo: TranspositionTable<f64>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver {
+            memo: TranspositionTable::new(ENDGAME_MEMO_SIZE),
+        }
+    }
+}
+
+impl Solver {
+    /// Probability of going out within k turns for every discard, sorted
+    /// so that the optimal one comes first.
+    fn discards(&mut self, full: &Hand, d: &Deck, k: u32) -> Vec<(Card, f64)> {
+        let mut actions: Vec<(Card, f64)> = distinct(full)
+            .into_iter()
+            .map(|i| {
+                let mut rest = full.clone();
+                let c = rest.remove(i);
+                (c, self.p_out(&rest, d, k))
+            })
+            .collect();
+        actions.sort_by(|a, b| b.1.total_cmp(&a.1));
+        actions
+    }
+
+    /// Probability of going out within k turns, before drawing.
+    fn p_out(&mut self, h: &Hand, d: &Deck, k: u32) -> f64 {
+        if k == 0 {
+            return 0.0;
+        }
+        let total = d.remaining();
+        if total == 0 {
+            return 0.0;
+        }
+        let key = hash_state(h, d) ^ (k as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        if let Some(&p) = self.memo.get(key) {
+            return p;
+        }
+
+        let mut p = 0.0;
+        for (c, n) in d.iter().filter(|&(_, n)| n > 0) {
+            let mut full = h.clone();
+            if full.try_push(c).is_err() {
+                return 0.0;
+            }
+            full.sort_unstable();
+            let mut rest_deck = d.clone();
+            rest_deck.remove(c).unwrap();
+            let v = match can_go_out(&full) {
+                true => 1.0,
+                false => self.best_discard(&full, &rest_deck, k - 1),
+            };
+            p += n as f64 / total as f64 * v;
+        }
+        self.memo.insert(key, p);
+        p
+    }
+
+    /// Highest probability of going out within k turns after a discard.
+    fn best_discard(&mut self, full: &Hand, d: &Deck, k: u32) -> f64 {
+        if k == 0 {
+            return 0.0;
+        }
+        let mut best: f64 = 0.0;
+        for i in distinct(full) {
+            let mut rest = full.clone();
+            rest.remove(i);
+            best = best.max(self.p_out(&rest, d, k));
+        }
+        best
+    }
+}
+
+/// Indices of the first occurrence of every distinct card in the hand.
+fn distinct(h: &Hand) -> Vec<usize> {
+    (0..h.len()).filter(|&i| !h[..i].contains(&h[i])).collect()
+}
+
+/// Creates a deck that contains only the given cards.
+#[cfg(test)]
+fn deck_of(cards: &str) -> Deck {
+    let pool = Hand::parse(cards).unwrap();
+    let mut d = Deck::new();
+    for c in Card::all() {
+        while d[c] > pool.iter().filter(|&&x| x == c).count() as u32 {
+            d.remove(c).unwrap();
+        }
+    }
+    d
+}
+
+#[test]
+fn endgame_exact_probability() {
+    // only a 4c or a 7c completes the street, the set is complete
+    let h = Hand::parse_sorted("5c 6c 9d 9h 9s Kd").unwrap();
+    let pool = deck_of("4c 7c Qh Kh");
+    assert_eq!(endgame_p_out(&h, &pool, 1), 0.5);
+    // after a miss, two of the remaining three cards complete the street
+    assert!((endgame_p_out(&h, &pool, 2) - (0.5 + 0.5 * 2.0 / 3.0)).abs() < 1e-9);
+    assert_eq!(endgame_p_out_bounded(&h, &pool, 1), Some(0.5));
+    assert_eq!(endgame_p_out_bounded(&h, &pool, ENDGAME_TURNS + 1), None);
+    // 7 distinct discards after every draw, 4 draws per turn
+    assert_eq!(endgame_draw_state_size(&h, &pool, 2), 4 * 7 * 4);
+
+    let (stock, discard) = endgame_piles(&h, Some(Card::parse("7c").unwrap()), &pool, 1);
+    assert_eq!(stock, 0.5);
+    assert_eq!(discard, Some(1.0));
+}
+
+#[test]
+fn endgame_policy() {
+    let full = Hand::parse_sorted("5c 6c 9d 9h 9s Qh Kd").unwrap();
+    let pool = deck_of("4c 7c Kh");
+    let actions = endgame_discards(&full, &pool, 1);
+    assert_eq!(actions.len(), 7);
+    assert!((actions[0].1 - 2.0 / 3.0).abs() < 1e-9);
+    assert!(actions[0].0.n == 12 || actions[0].0.n == 13);
+    assert_eq!(actions[6].1, 0.0);
+
+    assert!(endgame_state_size(&full, &pool, ENDGAME_TURNS) <= ENDGAME_MAX_STATES);
+    let params = EvalParams::default();
+    assert_eq!(
+        recommend_discard(&full, &pool, &params, &RoundState::default()),
+        Some(actions[0].0)
+    );
+}
+
+#[test]
+fn endgame_policy_later_turns() {
+    let full = Hand::parse_sorted("5c 6c 9d 9h 9s Qh Kd").unwrap();
+    let mut pool = deck_of("4c 7c Kh 2d");
+    let mut policy = endgame_plan(&full, &pool, 2);
+    let discard = policy.best().unwrap();
+    assert!(discard.n == 12 || discard.n == 13);
+
+    // after drawing the Kh, two of the remaining three cards complete the
+    // street
+    let mut full = full.clone();
+    full.retain(|c| *c != discard);
+    let kh = Card::parse("Kh").unwrap();
+    full.push(kh);
+    full.sort_unstable();
+    pool.remove(kh).unwrap();
+    policy.next_turn(&full, &pool);
+    assert_eq!(policy.turns, 1);
+    assert_eq!(policy.discards, endgame_discards(&full, &pool, 1));
+    assert!((policy.discards[0].1 - 2.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn endgame_overrides_heuristic() {
+    // dropping the 2c keeps two pairs, but the 9d or the Qc leave a street
+    // that any card of the pool completes within the horizon
+    let full = Hand::parse_sorted("2c 2s 3c 3s 9d Qc X").unwrap();
+    let pool = deck_of("3d 8c 2c 4c");
+    let params = EvalParams::default();
+    let round = RoundState::default();
+    let (heuristic, _) = best_discard_in_round(&full, &pool, &params, &round).unwrap();
+    let actions = endgame_discards(&full, &pool, ENDGAME_TURNS);
+    let p = |c: Card| actions.iter().find(|a| a.0 == c).unwrap().1;
+    assert!(endgame_state_size(&full, &pool, ENDGAME_TURNS) <= ENDGAME_MAX_STATES);
+
+    let chosen = recommend_discard(&full, &pool, &params, &round).unwrap();
+    assert_ne!(chosen, heuristic);
+    assert_eq!(p(chosen), 1.0);
+    assert!(p(heuristic) < 1.0);
+}
@@ -0,0 +1,234 @@
+use arrayvec::ArrayVec;
+
+use crate::card::*;
+use crate::gamedef::*;
+use crate::{MAX_HAND_SIZE, UNIQUE_CARDS};
+
+use super::decomp::*;
+use super::decomposition::*;
+use super::going_out::melds_with;
+use super::params::*;
+
+type CardGroup = ArrayVec<Card, MAX_HAND_SIZE>;
+
+/// Iterator over all decompositions of a hand into melds, quasi-melds and
+/// singles. Decompositions that only differ by the choice between two
+/// identical duplicate cards are yielded once.
+pub struct Decompositions<'a> {
+    hand: &'a Hand,
+    stack: Vec<Frame>,
+}
+
+struct Frame {
+    /// Number of copies of every card that isn't grouped yet.
+    remaining: [u8; UNIQUE_CARDS],
+    groups: Vec<CardGroup>,
+    /// Groups containing the lowest remaining card.
+    candidates: Vec<CardGroup>,
+    next: usize,
+}
+
+/// Enumerates all decompositions of the given hand, see `Decompositions`.
+pub fn all_decompositions(h: &Hand) -> Decompositions<'_> {
+    let mut remaining = [0; UNIQUE_CARDS];
+    for c in h.iter() {
+        remaining[c.to_index()] += 1;
+    }
+    Decompositions {
+        hand: h,
+        stack: vec![Frame::new(remaining, Vec::new(), None)],
+    }
+}
+
+/// Returns the k best decompositions of the hand, ranked by `decomp_score`.
+pub fn top_decompositions(h: &Hand, d: &Deck, params: &EvalParams, k: usize) -> Vec<Decomposition> {
+    let mut top: Vec<Decomposition> = Vec::with_capacity(k + 1);
+    for p in all_decompositions(h) {
+        let decomp = Decomposition::from_partition(&p, d, params);
+        if top.len() == k && top.last().is_none_or(|w| w.score >= decomp.score) {
+            continue;
+        }
+        let i = top.partition_point(|x| x.score >= decomp.score);
+        top.insert(i, decomp);
+        top.truncate(k);
+    }
+    top
+}
+
+impl Frame {
+    /// Creates a frame for the given remaining cards. `prev` is the group
+    /// chosen last. If it starts with the same card as the groups of this
+    /// frame, only groups that aren't smaller are allowed, which yields
+    /// groups of duplicate cards in a canonical order.
+    fn new(
+        remaining: [u8; UNIQUE_CARDS],
+        groups: Vec<CardGroup>,
+        prev: Option<&CardGroup>,
+    ) -> Self {
+        let candidates = match remaining.iter().position(|&n| n > 0) {
+            Some(i) => {
+                let c = Card::from_index(i);
+                let mut v = candidate_groups(c, &remaining);
+                if let Some(prev) = prev.filter(|p| lowest_regular(p) == Some(c)) {
+                    v.retain(|g| g >= prev);
+                }
+                v
+            }
+            None => Vec::new(),
+        };
+        Frame {
+            remaining,
+            groups,
+            candidates,
+            next: 0,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.remaining.iter().all(|&n| n == 0)
+    }
+}
+
+impl<'a> Iterator for Decompositions<'a> {
+    type Item = Partition<'a>;
+
+    fn next(&mut self) -> Option<Partition<'a>> {
+        loop {
+            let top = self.stack.last_mut()?;
+            if top.is_complete() {
+                let frame = self.stack.pop().unwrap();
+                return Some(to_partition(self.hand, &frame.groups));
+            }
+            if top.next == top.candidates.len() {
+                self.stack.pop();
+                continue;
+            }
+            let g = top.candidates[top.next].clone();
+            top.next += 1;
+            let mut remaining = top.remaining;
+            for c in g.iter() {
+                remaining[c.to_index()] -= 1;
+            }
+            let mut groups = top.groups.clone();
+            groups.push(g);
+            let frame = Frame::new(remaining, groups, top.candidates.get(top.next - 1));
+            self.stack.push(frame);
+        }
+    }
+}
+
+/// All singles, quasi-melds and melds that contain the given card, which
+/// must be the lowest remaining card. Thus all other regular cards of a
+/// group are higher than the given card. Jokers are only grouped into
+/// melds, see `melds_with`.
+fn candidate_groups(c: Card, remaining: &[u8; UNIQUE_CARDS]) -> Vec<CardGroup> {
+    let mut v: Vec<CardGroup> = Vec::new();
+    let group = |cards: &[Card]| cards.iter().copied().collect::<CardGroup>();
+    v.push(group(&[c]));
+    if c.suit == Suit::Joker {
+        return v;
+    }
+    let available = |x: Card| remaining[x.to_index()] > 0 && x != c;
+
+    // quasi-melds
+    for x in Card::all().skip(c.to_index() + 1).filter(|&x| available(x)) {
+        let g = group(&[c, x]);
+        if g.iter().collect::<Group>().quasi_kind().is_some() {
+            v.push(g);
+        }
+    }
+    // melds, completed by jokers where possible. Jokers can make a set and
+    // a run of the same cards, which are kept once.
+    let sorted = |g: &CardGroup| {
+        let mut g = g.clone();
+        g.sort_unstable();
+        g
+    };
+    for meld in melds_with(c, remaining) {
+        if !v.iter().any(|g| sorted(g) == sorted(&meld)) {
+            v.push(meld);
+        }
+    }
+    v
+}
+
+/// The card a group was chosen for, see `candidate_groups`.
+fn lowest_regular(g: &CardGroup) -> Option<Card> {
+    g.iter().copied().find(|c| c.suit != Suit::Joker)
+}
+
+/// Maps groups of cards onto the cards of the hand.
+fn to_partition<'a>(h: &'a Hand, groups: &[CardGroup]) -> Partition<'a> {
+    let mut used = [false; MAX_HAND_SIZE];
+    let mut p = Partition::new();
+    for g in groups.iter() {
+        let mut group = Group::new();
+        for c in g.iter() {
+            let i = (0..h.len()).find(|&i| !used[i] && h[i] == *c).unwrap();
+            used[i] = true;
+            group.push(&h[i]);
+        }
+        p.push(group);
+    }
+    p
+}
+
+#[test]
+fn enumerate_small_hand() {
+    // [5c] [6c] [7c], [5c 6c] [7c], [5c] [6c 7c], [5c 7c] [6c], [5c 6c 7c]
+    let h = Hand::parse_sorted("5c 6c 7c").unwrap();
+    assert_eq!(all_decompositions(&h).count(), 5);
+}
+
+#[test]
+fn enumerate_without_duplicates() {
+    // the two copies of 5c are interchangeable
+    let h = Hand::parse_sorted("5c 5c 5h").unwrap();
+    let all: Vec<String> = all_decompositions(&h)
+        .map(|p| DisplayPartition(&p).to_string())
+        .collect();
+    assert_eq!(all, vec!["[5c] [5c] [5h]", "[5c] [5c 5h]"]);
+}
+
+#[test]
+fn enumerate_joker_melds() {
+    let h = Hand::parse_sorted("5c 7c X").unwrap();
+    let all: Vec<String> = all_decompositions(&h)
+        .map(|p| DisplayPartition(&p).to_string())
+        .collect();
+    assert!(all.contains(&"[5c X 7c]".to_string()));
+    let top = top_decompositions(&h, &Deck::new(), &EvalParams::default(), 1);
+    assert_eq!(top[0].to_string(), "[5c X 7c]");
+
+    // a single regular card with two jokers is one group
+    let h = Hand::parse_sorted("7s X X").unwrap();
+    assert_eq!(all_decompositions(&h).count(), 2);
+}
+
+#[test]
+fn top_decompositions_ranked() {
+    // keep the street or keep the triple?
+    let h = Hand::parse_sorted("Ac 2c 3c 4c 4d 4h").unwrap();
+    let top = top_decompositions(&h, &Deck::new(), &EvalParams::default(), 3);
+    assert_eq!(top.len(), 3);
+    assert_eq!(top[0].to_string(), "[Ac 2c 3c] [4c 4d 4h]");
+    assert!(top[0].score >= top[1].score && top[1].score >= top[2].score);
+}
+
+#[test]
+fn greedy_is_lower_bound() {
+    let h = Hand::parse_sorted("5c 7c 9h 9s Kd X").unwrap();
+    let d = Deck::from_known(&h, &[], &[]).unwrap();
+    let params = EvalParams::default();
+    let greedy = Decomposition::of(&h, &d, &params);
+    assert_eq!(greedy.to_string(), "[5c X 7c] [Kd] [9h 9s]");
+    // the greedy decomposition is enumerated as well
+    let all: Vec<String> = all_decompositions(&h)
+        .map(|p| DisplayPartition(&p).to_string())
+        .collect();
+    assert!(all
+        .iter()
+        .any(|s| s.contains("[5c X 7c]") && s.contains("[9h 9s]")));
+    let top = top_decompositions(&h, &d, &params, 1);
+    assert!(top[0].score >= greedy.score);
+}
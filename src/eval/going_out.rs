@@ -0,0 +1,198 @@
+use core::fmt;
+
+use crate::card::*;
+use crate::gamedef::*;
+use crate::UNIQUE_CARDS;
+
+use super::*;
+
+/// A way of laying out a whole hand in a single turn: all cards but the
+/// discard form melds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoingOut {
+    pub melds: Vec<Hand>,
+    pub discard: Card,
+}
+
+/// Checks whether the given hand, which includes the card drawn or picked
+/// up this turn, can be laid out completely with exactly one discard.
+/// Unlike `optimal_decomposition`, this searches all ways of forming melds
+/// and of using jokers.
+pub fn going_out(full: &Hand) -> Option<GoingOut> {
+    for i in 0..full.len() {
+        if full[..i].contains(&full[i]) {
+            continue;
+        }
+        let mut counts = [0; UNIQUE_CARDS];
+        for (j, c) in full.iter().enumerate() {
+            if j != i {
+                counts[c.to_index()] += 1;
+            }
+        }
+        let mut melds = Vec::new();
+        if lay_out(&mut counts, &mut melds) {
+            return Some(GoingOut {
+                melds,
+                discard: full[i],
+            });
+        }
+    }
+    None
+}
+
+/// Same as `going_out` for a hand before the card `c` is drawn or picked
+/// up.
+pub fn going_out_with(h: &Hand, c: Card) -> Option<GoingOut> {
+    let mut full = h.clone();
+    full.try_push(c).ok()?;
+    full.sort_unstable();
+    going_out(&full)
+}
+
+/// Probability of going out in a single turn within the horizon of the
+/// config, without laying out any melds before. Uses the exact endgame
+/// search if the horizon and the state are small enough, and simulations
+/// otherwise.
+pub fn going_out_odds(h: &Hand, d: &Deck, params: &EvalParams, cfg: &SimConfig) -> f64 {
+    match endgame_p_out_bounded(h, d, cfg.horizon) {
+        Some(p) => p,
+        None => turns_to_go_out(h, d, params, cfg).p_within(cfg.horizon),
+    }
+}
+
+/// Splits the counted cards into melds. The lowest regular card must be
+/// part of some meld, so every meld containing it is tried in turn.
+fn lay_out(counts: &mut [u8; UNIQUE_CARDS], melds: &mut Vec<Hand>) -> bool {
+    let joker = JOKER_CARD.to_index();
+    let c = match (0..joker).find(|&i| counts[i] > 0) {
+        Some(i) => Card::from_index(i),
+        // jokers can't form a meld on their own
+        None => return counts[joker] == 0,
+    };
+    for meld in melds_with(c, counts) {
+        for x in meld.iter() {
+            counts[x.to_index()] -= 1;
+        }
+        melds.push(meld);
+        if lay_out(counts, melds) {
+            return true;
+        }
+        let meld = melds.pop().unwrap();
+        for x in meld.iter() {
+            counts[x.to_index()] += 1;
+        }
+    }
+    false
+}
+
+/// All melds that contain the card, which must be the lowest remaining
+/// regular card, and otherwise only available cards.
+pub(super) fn melds_with(c: Card, counts: &[u8; UNIQUE_CARDS]) -> Vec<Hand> {
+    let jokers = counts[JOKER_CARD.to_index()] as usize;
+    let mut v = Vec::new();
+
+    // sets, completed by jokers
+    let others: Vec<Card> = Suit::standard()
+        .filter(|&&s| s.index() > c.suit.index())
+        .map(|&suit| Card { n: c.n, suit })
+        .filter(|x| counts[x.to_index()] > 0)
+        .collect();
+    for mask in 0..(1u32 << others.len()) {
+        let mut set: Hand = Some(c).into_iter().collect();
+        set.extend(
+            (0..others.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| others[i]),
+        );
+        for k in 0..=jokers.min(MAX_SET_SIZE - set.len()) {
+            if set.len() + k >= 3 {
+                v.push(
+                    set.iter()
+                        .copied()
+                        .chain((0..k).map(|_| JOKER_CARD))
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    // runs, where jokers may stand in below the card
+    for below in 0..=jokers.min(c.n.to_int() as usize - 1) {
+        let mut run: Hand = (0..below).map(|_| JOKER_CARD).collect();
+        run.push(c);
+        extend_runs(&mut run, c, jokers - below, counts, &mut v);
+    }
+    v
+}
+
+/// Adds all runs that start with the given cards, ending in `last`.
+fn extend_runs(
+    run: &mut Hand,
+    last: Card,
+    jokers: usize,
+    counts: &[u8; UNIQUE_CARDS],
+    v: &mut Vec<Hand>,
+) {
+    if run.len() >= 3 {
+        v.push(run.clone());
+    }
+    let next = match last.next() {
+        Some(next) if !run.is_full() => next,
+        _ => return,
+    };
+    if counts[next.to_index()] > 0 {
+        run.push(next);
+        extend_runs(run, next, jokers, counts, v);
+        run.pop();
+    }
+    if jokers > 0 {
+        run.push(JOKER_CARD);
+        extend_runs(run, next, jokers - 1, counts, v);
+        run.pop();
+    }
+}
+
+impl fmt::Display for GoingOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for m in self.melds.iter() {
+            write!(f, "[")?;
+            fmt::Display::fmt(&m.display(), f)?;
+            write!(f, "] ")?;
+        }
+        write!(f, "discard ")?;
+        fmt::Display::fmt(&self.discard, f)
+    }
+}
+
+#[test]
+fn going_out_finds_split() {
+    // the greedy decomposition keeps the street Ac-4c together
+    let h = Hand::parse_sorted("Ac 2c 3c 4c 4d 4h").unwrap();
+    let out = going_out_with(&h, Card::parse("Kd").unwrap()).unwrap();
+    assert_eq!(out.to_string(), "[Ac 2c 3c] [4c 4d 4h] discard Kd");
+
+    let h = Hand::parse_sorted("5c 7c X 9h 9s Jd Qd Kd 2s").unwrap();
+    assert!(going_out(&h).is_none());
+    let out = going_out_with(&h, Card::parse("9d").unwrap()).unwrap();
+    assert_eq!(out.melds.len(), 3);
+    assert_eq!(out.discard, Card::parse("2s").unwrap());
+    assert!(out.melds.iter().all(|m| validate_meld(m.iter()).is_ok()));
+}
+
+#[test]
+fn going_out_probability() {
+    let cfg = SimConfig {
+        samples: 200,
+        horizon: 2,
+        seed: 3,
+    };
+    let params = EvalParams::default();
+    let near = Hand::parse_sorted("Ac 2c 3c 5h 5d 5s 9d 10d").unwrap();
+    let d = Deck::from_known(&near, &[], &[]).unwrap();
+    let p = going_out_odds(&near, &d, &params, &cfg);
+    assert!(p > 0.0 && p < 1.0);
+
+    let far = Hand::parse_sorted("Ac 4d 7h 10s 2s 5c 8d Jh").unwrap();
+    let d = Deck::from_known(&far, &[], &[]).unwrap();
+    assert_eq!(going_out_odds(&far, &d, &params, &cfg), 0.0);
+}
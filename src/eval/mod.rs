@@ -1,18 +1,201 @@
-mod decomp_score;
+mod batch;
+mod danger;
 mod decomp;
+mod decomp_score;
+mod decomposition;
+mod endgame;
+mod enumerate;
+mod going_out;
+mod monte_carlo;
+mod opening;
+mod params;
+mod penalty;
+mod turns;
 
 #[cfg(test)]
 mod decomp_test;
 #[cfg(test)]
 mod scoring_test;
 
+use arrayvec::ArrayVec;
+
 use crate::*;
 
+use decomp_score::*;
+
+pub use batch::*;
+pub use danger::*;
+pub use decomp::{
+    optimal_decomposition, parse_partition, DisplayPartition, Group, GroupCharacteristics,
+    Partition, QuasiKind, MAX_DECOMP_COUNT,
+};
+pub use decomposition::*;
+pub use endgame::*;
+pub use enumerate::*;
+pub use going_out::*;
+pub use monte_carlo::*;
+pub use opening::*;
+pub use params::*;
+pub use penalty::*;
+pub use turns::*;
+
 /// Computes an approximate quality score for a given hand and deck.
 ///
 /// The metric is relative, so that if `score(h1,d) > score(h2,d)`, then
 /// hand `h2` should have a higher expected winrate. Note that h1 and h2
 /// MUST have the same number of cards to produce a meaningful comparison.
-pub fn score_hand(_h: &Hand, _d: &Deck) -> f64 {
-    0.0
+pub fn score_hand(h: &Hand, d: &Deck, params: &EvalParams) -> f64 {
+    let sorted = sorted_hand(h);
+    let p = optimal_decomposition(&sorted);
+    decomp_score(&p, d, params)
+}
+
+/// The decompositions depend on the order of the cards, so the scores are
+/// computed on the sorted hand.
+fn sorted_hand(h: &Hand) -> Hand {
+    let mut sorted = h.clone();
+    sorted.sort_unstable();
+    sorted
+}
+
+/// Number of distinct cards in the hand that can take the place of a
+/// joker on the table.
+pub fn freeing_cards(h: &Hand, table: &Table) -> usize {
+    (0..h.len())
+        .filter(|&i| !h[..i].contains(&h[i]) && !table.joker_exchanges(h[i]).is_empty())
+        .count()
+}
+
+/// Same as `score_hand`, but also values the cards that can free a joker
+/// from the table, weighted by `params.joker_exchange`.
+pub fn score_hand_on_table(h: &Hand, d: &Deck, table: &Table, params: &EvalParams) -> f64 {
+    score_hand(h, d, params) + params.joker_exchange * freeing_cards(h, table) as f64
+}
+
+/// Memo tables shared by the search functions. Scores are keyed by the
+/// hand, the deck and the evaluation weights, canonicalized under suit
+/// symmetry. Decompositions are keyed by the multiset of cards.
+#[derive(Clone, Debug)]
+pub struct SearchCache {
+    scores: TranspositionTable<f64>,
+    decomps: TranspositionTable<ArrayVec<ArrayVec<Card, MAX_HAND_SIZE>, MAX_DECOMP_COUNT>>,
+}
+
+impl SearchCache {
+    /// Creates a cache with the given number of slots per table.
+    pub fn new(capacity: usize) -> Self {
+        SearchCache {
+            scores: TranspositionTable::new(capacity),
+            decomps: TranspositionTable::new(capacity),
+        }
+    }
+}
+
+impl Default for SearchCache {
+    fn default() -> Self {
+        SearchCache::new(1 << 16)
+    }
+}
+
+/// Same as `score_hand`, but looks up and stores the result in the cache.
+pub fn score_hand_cached(h: &Hand, d: &Deck, params: &EvalParams, cache: &mut SearchCache) -> f64 {
+    let mut key = hash_state(h, d);
+    let mut p = *params;
+    for w in p.fields() {
+        key = key.rotate_left(7) ^ w.to_bits();
+    }
+    if let Some(&score) = cache.scores.get(key) {
+        return score;
+    }
+    let score = decomp_score(&optimal_decomposition_cached(h, cache), d, params);
+    cache.scores.insert(key, score);
+    score
+}
+
+/// Same as `optimal_decomposition` of the sorted hand, but looks up and
+/// stores the result in the cache. The groups of the decomposition are
+/// mapped back onto the cards of the given hand.
+pub fn optimal_decomposition_cached<'a>(h: &'a Hand, cache: &mut SearchCache) -> Partition<'a> {
+    let key = hash_hand(h);
+    let groups = match cache.decomps.get(key) {
+        Some(groups) => groups.clone(),
+        None => {
+            let sorted = sorted_hand(h);
+            let groups: ArrayVec<_, MAX_DECOMP_COUNT> = optimal_decomposition(&sorted)
+                .iter()
+                .map(|g| g.iter().map(|&&c| c).collect())
+                .collect();
+            cache.decomps.insert(key, groups.clone());
+            groups
+        }
+    };
+    let mut used = [false; MAX_HAND_SIZE];
+    let mut p = Partition::new();
+    for g in groups.iter() {
+        let mut group = Group::new();
+        for c in g.iter() {
+            let i = (0..h.len()).find(|&i| !used[i] && h[i] == *c).unwrap();
+            used[i] = true;
+            group.push(&h[i]);
+        }
+        p.push(group);
+    }
+    p
+}
+
+/// Finds the card whose removal leaves the highest scoring hand.
+///
+/// Returns the discarded card together with the score of the remaining
+/// hand, or `None` if the hand is empty.
+pub fn best_discard(h: &Hand, d: &Deck, params: &EvalParams) -> Option<(Card, f64)> {
+    best_discard_by(h, |_, rest| Some(score_hand(rest, d, params)))
+}
+
+/// Tries every distinct discard and returns the card with the highest
+/// value, together with that value. `value` is given the discarded card
+/// and the rest of the hand, and returns `None` if the card must not be
+/// discarded. On ties, the first card wins.
+pub(super) fn best_discard_by<F>(h: &Hand, mut value: F) -> Option<(Card, f64)>
+where
+    F: FnMut(Card, &Hand) -> Option<f64>,
+{
+    let mut best: Option<(Card, f64)> = None;
+    for i in 0..h.len() {
+        // duplicates leave identical hands behind
+        if h[..i].contains(&h[i]) {
+            continue;
+        }
+        let mut rest = h.clone();
+        let c = rest.remove(i);
+        let v = match value(c, &rest) {
+            Some(v) => v,
+            None => continue,
+        };
+        match best {
+            Some((_, b)) if b >= v => {}
+            _ => best = Some((c, v)),
+        }
+    }
+    best
+}
+
+/// Finds the discard that trades off the quality of the remaining hand,
+/// see `score_hand_on_table`, against the danger of feeding the next
+/// player. `params.danger` weighs the danger (between 0 and 1) against
+/// the score of the hand.
+///
+/// Returns the discarded card together with its combined value.
+pub fn safe_discard(
+    h: &Hand,
+    d: &Deck,
+    table: &Table,
+    next: &OpponentModel,
+    params: &EvalParams,
+) -> Option<(Card, f64)> {
+    best_discard_by(h, |c, rest| {
+        Some(
+            score_hand_on_table(rest, d, table, params)
+                - params.danger * discard_danger(c, table, next),
+        )
+    })
 }
@@ -0,0 +1,175 @@
+use core::fmt;
+
+use crate::gamedef::*;
+use crate::rules::RuleSet;
+
+use super::*;
+
+/// How `score_hand_with` evaluates a hand.
+#[derive(Copy, Clone, Debug)]
+pub enum ScoreMode {
+    /// The static `decomp_score` of the optimal decomposition.
+    Static,
+    /// The fraction of simulations that reach opening or going out under
+    /// the given rules, see `score_hand_monte_carlo`.
+    MonteCarlo(SimConfig, RuleSet),
+}
+
+/// Result of `score_hand_monte_carlo`.
+#[derive(Copy, Clone, Debug)]
+pub struct MonteCarloScore {
+    /// Fraction of simulations that reached opening or going out.
+    pub score: f64,
+    /// Standard error of `score`.
+    pub std_error: f64,
+    /// Fraction of simulations that could open.
+    pub p_open: f64,
+    /// Fraction of simulations that went out.
+    pub p_out: f64,
+    pub samples: u32,
+}
+
+/// Scores the hand according to the given mode.
+pub fn score_hand_with(h: &Hand, d: &Deck, params: &EvalParams, mode: &ScoreMode) -> f64 {
+    match mode {
+        ScoreMode::Static => score_hand(h, d, params),
+        ScoreMode::MonteCarlo(cfg, rules) => score_hand_monte_carlo(h, d, params, rules, cfg).score,
+    }
+}
+
+/// Monte Carlo alternative to `score_hand`. Samples draw sequences from
+/// the unseen pool and plays them out greedily: every turn a card is drawn
+/// and the card whose removal costs the least according to `score_hand`
+/// is discarded. Measures the fraction of simulations that can open under
+/// the given rules, see `best_opening`, or go out within the horizon.
+pub fn score_hand_monte_carlo(
+    h: &Hand,
+    d: &Deck,
+    params: &EvalParams,
+    rules: &RuleSet,
+    cfg: &SimConfig,
+) -> MonteCarloScore {
+    let results = rollouts(cfg.samples, cfg.seed, default_threads(), |rng| {
+        play_greedy(h, d, params, rules, cfg.horizon, rng)
+    });
+    let n = results.len().max(1) as f64;
+    let fraction =
+        |f: &dyn Fn(&(bool, bool)) -> bool| results.iter().filter(|r| f(r)).count() as f64 / n;
+    let score = fraction(&|&(open, out)| open || out);
+    MonteCarloScore {
+        score,
+        std_error: (score * (1.0 - score) / n).sqrt(),
+        p_open: fraction(&|r| r.0),
+        p_out: fraction(&|r| r.1),
+        samples: results.len() as u32,
+    }
+}
+
+/// Plays out a single draw sequence and returns whether the hand could
+/// open and whether it went out. A hand of more than `rules.deal_size`
+/// cards includes the card drawn on the first turn.
+fn play_greedy(
+    h: &Hand,
+    d: &Deck,
+    params: &EvalParams,
+    rules: &RuleSet,
+    horizon: u32,
+    rng: &fastrand::Rng,
+) -> (bool, bool) {
+    let mut hand = h.clone();
+    let mut deck = d.clone();
+    let mut open = false;
+    // a full hand has drawn already this turn, so it starts by discarding
+    let mut drawn = hand.len() > rules.deal_size;
+    for _ in 0..horizon {
+        if !drawn {
+            match deck.remove_random_with(rng) {
+                Ok(c) => hand.push(c),
+                Err(_) => break,
+            }
+            hand.sort_unstable();
+        }
+        drawn = false;
+        if can_go_out(&hand) {
+            return (open, true);
+        }
+        open = open || best_opening(&hand, rules).is_some();
+        let (discard, _) = match best_discard(&hand, &deck, params) {
+            Some(x) => x,
+            None => break,
+        };
+        let i = hand.iter().position(|&x| x == discard).unwrap();
+        hand.remove(i);
+    }
+    (open, false)
+}
+
+impl fmt::Display for MonteCarloScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.3} ± {:.3} (opens {:.1}%, goes out {:.1}%, {} samples)",
+            self.score,
+            self.std_error,
+            100.0 * self.p_open,
+            100.0 * self.p_out,
+            self.samples
+        )
+    }
+}
+
+#[test]
+fn monte_carlo_ranks_hands() {
+    let cfg = SimConfig {
+        samples: 60,
+        horizon: 4,
+        seed: 5,
+    };
+    let params = EvalParams::default();
+    let strong = Hand::parse_sorted("Jc Qc Kc 10h 10d 9h 9s 2s 5c 8d 3h 7s 4d Ah").unwrap();
+    let weak = Hand::parse_sorted("2c 4d 6h 8s 10c Qd Ah 3s 5c 7d 9h Js Kc 2d").unwrap();
+    let d = Deck::from_known(&strong, &[], &[]).unwrap();
+    let rules = RuleSet::default();
+    let a = score_hand_monte_carlo(&strong, &d, &params, &rules, &cfg);
+    let d = Deck::from_known(&weak, &[], &[]).unwrap();
+    let b = score_hand_monte_carlo(&weak, &d, &params, &rules, &cfg);
+
+    assert_eq!(a.samples, 60);
+    assert!(a.score > b.score);
+    assert!(a.std_error > 0.0 && a.std_error < 0.1);
+    assert_eq!(
+        score_hand_with(&weak, &d, &params, &ScoreMode::MonteCarlo(cfg, rules)),
+        b.score
+    );
+
+    // nothing is worth opening under a prohibitive threshold
+    let strict = RuleSet {
+        opening_points: 1000,
+        ..rules
+    };
+    let d = Deck::from_known(&strong, &[], &[]).unwrap();
+    let c = score_hand_monte_carlo(&strong, &d, &params, &strict, &cfg);
+    assert!(a.p_open > 0.0);
+    assert_eq!(c.p_open, 0.0);
+}
+
+#[test]
+fn monte_carlo_full_hand() {
+    let cfg = SimConfig {
+        samples: 20,
+        horizon: 3,
+        seed: 2,
+    };
+    let params = EvalParams::default();
+    let rules = RuleSet::default();
+    // already laid out, except for the discard
+    let out = Hand::parse_sorted("Ac 2c 3c 4c 5h 5d 5s 9d 10d Jd Jh Qh Kh X 7c").unwrap();
+    assert_eq!(out.len(), MAX_HAND_SIZE);
+    let d = Deck::from_known(&out, &[], &[]).unwrap();
+    let score = score_hand_monte_carlo(&out, &d, &params, &rules, &cfg);
+    assert_eq!(score.p_out, 1.0);
+
+    let strong = Hand::parse_sorted("Jc Qc Kc 10h 10d 9h 9s 2s 5c 8d 3h 7s 4d Ah Ad").unwrap();
+    let d = Deck::from_known(&strong, &[], &[]).unwrap();
+    assert!(score_hand_monte_carlo(&strong, &d, &params, &rules, &cfg).score > 0.0);
+}
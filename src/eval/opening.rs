@@ -0,0 +1,360 @@
+use core::fmt;
+
+use crate::card::*;
+use crate::gamedef::*;
+use crate::meld::*;
+use crate::rules::RuleSet;
+use crate::UNIQUE_CARDS;
+
+use super::going_out::melds_with;
+use super::*;
+
+/// Bonus for going out after having opened earlier.
+pub const GOING_OUT_BONUS: f64 = 40.0;
+
+/// Bonus for going out all at once without having opened before, which
+/// counts double.
+pub const HAND_OUT_BONUS: f64 = 80.0;
+
+/// Penalty for a player that hasn't opened when somebody else goes out.
+pub const UNOPENED_PENALTY: f64 = 100.0;
+
+/// The melds a player opens with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opening {
+    pub melds: Vec<Hand>,
+    /// Total value of the melds, see `meld_points`.
+    pub points: u32,
+}
+
+/// Expected result of one choice, estimated by simulation.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OpeningOutcome {
+    /// Expected score at the end of the round. Penalties are positive and
+    /// going out is negative, so lower is better.
+    pub expected_score: f64,
+    /// Probability of going out before an opponent does.
+    pub p_out: f64,
+    /// Number of unseen cards that let us go out on the next turn.
+    pub outs: u32,
+    /// Expected penalty if an opponent goes out before our next turn.
+    pub penalty_risk: f64,
+}
+
+/// Comparison of opening now with holding back, see `decide_opening`.
+#[derive(Clone, Debug)]
+pub struct OpeningDecision {
+    pub opening: Opening,
+    pub open_now: OpeningOutcome,
+    pub hold_back: OpeningOutcome,
+    /// Expected number of turns until an opponent goes out, within the
+    /// simulation horizon.
+    pub turns_remaining: f64,
+}
+
+impl OpeningDecision {
+    /// Returns true if opening now has the better expected score.
+    pub fn should_open(&self) -> bool {
+        self.open_now.expected_score <= self.hold_back.expected_score
+    }
+}
+
+/// Finds the melds of the highest total value, such that at least one
+/// card is left to discard. Returns `None` if they aren't worth enough to
+/// open under the given rules.
+pub fn best_opening(full: &Hand, rules: &RuleSet) -> Option<Opening> {
+    let mut counts = [0; UNIQUE_CARDS];
+    for c in full.iter() {
+        counts[c.to_index()] += 1;
+    }
+    let mut best = Opening {
+        melds: Vec::new(),
+        points: 0,
+    };
+    search_opening(&mut counts, 0, full.len(), &mut Vec::new(), &mut best);
+    Some(best).filter(|o| o.points >= rules.opening_points)
+}
+
+/// Branches on the lowest card from index `from` on: either it stays in
+/// the hand, or it's part of one of the melds containing it.
+fn search_opening(
+    counts: &mut [u8; UNIQUE_CARDS],
+    from: usize,
+    left: usize,
+    melds: &mut Vec<Hand>,
+    best: &mut Opening,
+) {
+    let points: u32 = melds.iter().map(|m| meld_points(m.iter()).unwrap()).sum();
+    if points > best.points && left > 0 {
+        best.melds = melds.clone();
+        best.points = points;
+    }
+    let i = match (from..JOKER_CARD.to_index()).find(|&i| counts[i] > 0) {
+        Some(i) => i,
+        None => return,
+    };
+    let c = Card::from_index(i);
+    for meld in melds_with(c, counts) {
+        if meld.len() >= left {
+            continue;
+        }
+        for x in meld.iter() {
+            counts[x.to_index()] -= 1;
+        }
+        melds.push(meld);
+        search_opening(counts, i, left - melds[melds.len() - 1].len(), melds, best);
+        let meld = melds.pop().unwrap();
+        for x in meld.iter() {
+            counts[x.to_index()] += 1;
+        }
+    }
+    // keep all copies of the card in the hand
+    search_opening(counts, i + 1, left, melds, best);
+}
+
+/// Compares opening now with the best opening against holding back, for
+/// a hand that includes the card drawn this turn. Holding back means
+/// waiting to go out all at once.
+///
+/// Both choices are played out over the same draw sequences from the
+/// unseen pool: we discard with `best_discard_in_round`, draw, lay off
+/// onto our own melds once opened, and go out if possible. Before each of
+/// our turns, an opponent goes out with the probability given by the round
+/// state. Reaching the horizon counts as an opponent going out.
+///
+/// Returns `None` if the hand can't open.
+pub fn decide_opening(
+    full: &Hand,
+    d: &Deck,
+    params: &EvalParams,
+    round: &RoundState,
+    rules: &RuleSet,
+    cfg: &SimConfig,
+) -> Option<OpeningDecision> {
+    let opening = best_opening(full, rules)?;
+    let mut rest = full.clone();
+    for c in opening.melds.iter().flatten() {
+        let i = rest.iter().position(|x| x == c).unwrap();
+        rest.remove(i);
+    }
+    let table = Table {
+        melds: opening.melds.clone(),
+    };
+
+    let results = rollouts(cfg.samples, cfg.seed, default_threads(), |rng| {
+        let seed = rng.u64(..);
+        let open = play_out(&rest, Some(&table), d, params, round, cfg.horizon, seed);
+        let hold = play_out(full, None, d, params, round, cfg.horizon, seed);
+        (open, hold)
+    });
+    let samples = results.len().max(1) as f64;
+    let summarize = |outcomes: Vec<(f64, bool)>, hand: &Hand, table: Option<&Table>| {
+        // the hand after the discard of this turn
+        let mut hand = hand.clone();
+        if let Some((c, _)) = best_discard_in_round(&hand, d, params, round) {
+            let i = hand.iter().position(|&x| x == c).unwrap();
+            hand.remove(i);
+        }
+        let penalty = match table {
+            Some(_) => unmelded_points(&hand) as f64,
+            None => UNOPENED_PENALTY,
+        };
+        OpeningOutcome {
+            expected_score: outcomes.iter().map(|o| o.0).sum::<f64>() / samples,
+            p_out: outcomes.iter().filter(|o| o.1).count() as f64 / samples,
+            outs: d
+                .iter()
+                .filter(|&(c, n)| n > 0 && finishes_with(&hand, c, table))
+                .map(|(_, n)| n)
+                .sum(),
+            penalty_risk: round.p_opponent_out() * penalty,
+        }
+    };
+    let open_now = summarize(results.iter().map(|r| r.0).collect(), &rest, Some(&table));
+    let hold_back = summarize(results.iter().map(|r| r.1).collect(), full, None);
+
+    let mut survival = 1.0;
+    let mut turns_remaining = 0.0;
+    for t in 0..cfg.horizon {
+        let state = RoundState {
+            turn: round.turn + t,
+            ..round.clone()
+        };
+        survival *= 1.0 - state.p_opponent_out();
+        turns_remaining += survival;
+    }
+    Some(OpeningDecision {
+        opening,
+        open_now,
+        hold_back,
+        turns_remaining,
+    })
+}
+
+/// Plays out a single continuation, starting with the discard of this
+/// turn. Returns the score and whether we went out. `table` holds our own
+/// melds if we have opened.
+fn play_out(
+    h: &Hand,
+    table: Option<&Table>,
+    d: &Deck,
+    params: &EvalParams,
+    round: &RoundState,
+    horizon: u32,
+    seed: u64,
+) -> (f64, bool) {
+    let rng = fastrand::Rng::with_seed(seed);
+    let mut hand = h.clone();
+    let mut deck = d.clone();
+    let mut table = table.cloned();
+    let bonus = match table {
+        Some(_) => GOING_OUT_BONUS,
+        None => HAND_OUT_BONUS,
+    };
+    for t in 0..=horizon {
+        let state = RoundState {
+            turn: round.turn + t,
+            ..round.clone()
+        };
+        // jokers are kept for new melds, unless they let us go out
+        if let Some(table) = table.as_mut() {
+            lay_off_all(&mut hand, table, false);
+        }
+        discard_keeping_jokers(&mut hand, &deck, params, &state);
+        if hand.is_empty() {
+            // laid out everything on the turn of opening
+            return (-HAND_OUT_BONUS, true);
+        }
+        if t == horizon || rng.f64() < state.p_opponent_out() {
+            break;
+        }
+        let c = match deck.remove_random_with(&rng) {
+            Ok(c) => c,
+            Err(_) => break,
+        };
+        if finishes_with(&hand, c, table.as_ref()) {
+            return (-bonus, true);
+        }
+        hand.push(c);
+        hand.sort_unstable();
+    }
+    match table {
+        Some(_) => (unmelded_points(&hand) as f64, false),
+        None => (UNOPENED_PENALTY, false),
+    }
+}
+
+/// Discards the card that leaves the best hand according to
+/// `score_hand_in_round`. Jokers are only discarded if there's nothing
+/// else, as the next player could pick them up.
+fn discard_keeping_jokers(hand: &mut Hand, d: &Deck, params: &EvalParams, round: &RoundState) {
+    let keep_jokers = hand.iter().any(|c| c.suit != Suit::Joker);
+    let best = best_discard_by(hand, |c, rest| match keep_jokers && c.suit == Suit::Joker {
+        true => None,
+        false => Some(score_hand_in_round(rest, d, params, round)),
+    });
+    if let Some((c, _)) = best {
+        let i = hand.iter().position(|&x| x == c).unwrap();
+        hand.remove(i);
+    }
+}
+
+/// Returns true if drawing the card lets us go out, laying off onto our
+/// own melds first if we have opened.
+fn finishes_with(h: &Hand, c: Card, table: Option<&Table>) -> bool {
+    let mut hand = h.clone();
+    if hand.try_push(c).is_err() {
+        return false;
+    }
+    if let Some(table) = table {
+        lay_off_all(&mut hand, &mut table.clone(), true);
+        if hand.len() <= 1 {
+            return true;
+        }
+    }
+    can_go_out(&hand)
+}
+
+/// Lays off cards onto the table as long as one is accepted, keeping at
+/// least one card to discard.
+fn lay_off_all(hand: &mut Hand, table: &mut Table, jokers: bool) {
+    while hand.len() > 1 {
+        let accepted = |c: Card| (jokers || c.suit != Suit::Joker) && table.accepts(c);
+        match (0..hand.len()).find(|&i| accepted(hand[i])) {
+            Some(i) => {
+                table.lay_off(hand.remove(i));
+            }
+            None => break,
+        }
+    }
+}
+
+impl fmt::Display for OpeningOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected score {:+.1}, goes out {:.1}%, {} outs, penalty risk {:.1}",
+            self.expected_score,
+            100.0 * self.p_out,
+            self.outs,
+            self.penalty_risk
+        )
+    }
+}
+
+impl fmt::Display for OpeningDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "opening with {} points:", self.opening.points)?;
+        for m in self.opening.melds.iter() {
+            write!(f, " [{}]", m.display())?;
+        }
+        writeln!(f)?;
+        writeln!(f, "open now:  {}", self.open_now)?;
+        writeln!(f, "hold back: {}", self.hold_back)?;
+        writeln!(f, "turns remaining: {:.1}", self.turns_remaining)?;
+        let choice = match self.should_open() {
+            true => "open now",
+            false => "hold back",
+        };
+        write!(f, "recommendation: {}", choice)
+    }
+}
+
+#[test]
+fn opening_value() {
+    let h = Hand::parse_sorted("Jc Qc Kc Ad Ah As 2h 3h 4h 9d").unwrap();
+    let rules = RuleSet::default();
+    let o = best_opening(&h, &rules).unwrap();
+    assert_eq!(o.points, 30 + 30 + 9);
+    assert_eq!(o.melds.len(), 3);
+
+    // 30 + 9 isn't enough
+    let h = Hand::parse_sorted("Jc Qc Kc 2h 3h 4h 9d").unwrap();
+    assert!(best_opening(&h, &rules).is_none());
+    let relaxed = RuleSet {
+        opening_points: 39,
+        ..rules
+    };
+    assert_eq!(best_opening(&h, &relaxed).unwrap().points, 39);
+}
+
+#[test]
+fn open_late_in_the_round() {
+    let h = Hand::parse_sorted("Jc Qc Kc Ad Ah As 10h 10d 10s 2d 5s 7h 9c Kd").unwrap();
+    let d = Deck::from_known(&h, &[], &[]).unwrap();
+    let cfg = SimConfig {
+        samples: 40,
+        horizon: 8,
+        seed: 1,
+    };
+    let round = RoundState {
+        turn: EXPECTED_ROUND_TURNS,
+        opponent_cards: vec![4, 6],
+    };
+    let rules = RuleSet::default();
+    let decision = decide_opening(&h, &d, &EvalParams::default(), &round, &rules, &cfg).unwrap();
+    assert_eq!(decision.opening.points, 90);
+    assert!(decision.should_open());
+    assert!(decision.hold_back.penalty_risk > decision.open_now.penalty_risk);
+    assert!(decision.turns_remaining < 3.0);
+}
@@ -0,0 +1,172 @@
+use core::fmt;
+use std::path::Path;
+
+use crate::Error;
+
+/// All weights used by the evaluation functions. The default values
+/// reproduce the original, hard-coded heuristic.
+///
+/// Parameter sets can be stored in simple text files with one
+/// `key = value` pair per line, see `EvalParams::parse`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EvalParams {
+    /// Value per card of a group that is neither meld nor quasi-meld.
+    pub single: f64,
+    /// Value per card of a quasi-meld of two consecutive cards, e.g. 5c 6c.
+    pub quasi_street: f64,
+    /// Value per card of a quasi-meld with a gap, e.g. 5c 7c.
+    pub quasi_gap: f64,
+    /// Value per card of a quasi-meld of the same number, e.g. 5c 5h.
+    pub quasi_set: f64,
+    /// Value per card of a meld.
+    pub meld_card: f64,
+    /// Additional value per meld card beyond the third.
+    pub long_meld: f64,
+    /// Factor applied to the odds of drawing a card that extends a meld.
+    pub extension: f64,
+    /// Additional value per joker in the hand.
+    pub joker: f64,
+    /// Penalty per point of the cards that are not part of a meld.
+    pub point_penalty: f64,
+    /// Weight of the danger of a discard, see `discard_danger`.
+    pub danger: f64,
+    /// Weight of the expected penalty of the cards that are not part of a
+    /// meld, see `expected_penalty`.
+    pub penalty_risk: f64,
+    /// Value per card in the hand that can take the place of a joker on
+    /// the table, see `freeing_cards`.
+    pub joker_exchange: f64,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            single: 0.05,
+            quasi_street: 0.05,
+            quasi_gap: 0.05,
+            quasi_set: 0.05,
+            meld_card: 1.0,
+            long_meld: 0.0,
+            extension: 1.0,
+            joker: 0.0,
+            point_penalty: 0.0,
+            danger: 1.0,
+            penalty_risk: 0.1,
+            joker_exchange: 0.5,
+        }
+    }
+}
+
+impl EvalParams {
+    /// Names of all parameters, in the order of `fields`.
+    pub const KEYS: [&'static str; 12] = [
+        "single",
+        "quasi_street",
+        "quasi_gap",
+        "quasi_set",
+        "meld_card",
+        "long_meld",
+        "extension",
+        "joker",
+        "point_penalty",
+        "danger",
+        "penalty_risk",
+        "joker_exchange",
+    ];
+
+    /// Mutable references to all parameters, in the order of `KEYS`.
+    pub fn fields(&mut self) -> [&mut f64; 12] {
+        [
+            &mut self.single,
+            &mut self.quasi_street,
+            &mut self.quasi_gap,
+            &mut self.quasi_set,
+            &mut self.meld_card,
+            &mut self.long_meld,
+            &mut self.extension,
+            &mut self.joker,
+            &mut self.point_penalty,
+            &mut self.danger,
+            &mut self.penalty_risk,
+            &mut self.joker_exchange,
+        ]
+    }
+
+    /// Parses a parameter set from `key = value` lines. Empty lines and
+    /// lines starting with '#' are ignored. Missing keys keep their
+    /// default value.
+    pub fn parse(s: &str) -> Result<EvalParams, Error> {
+        let mut params = EvalParams::default();
+        // every token is a subslice of `s`, which gives its byte offset
+        let at = |token: &str| token.as_ptr() as usize - s.as_ptr() as usize;
+        for line in s.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| Error::parse(line, at(line), "a line like 'key = value'"))?;
+            let (key, value) = (key.trim(), value.trim());
+            let i = EvalParams::KEYS
+                .iter()
+                .position(|&k| k == key)
+                .ok_or_else(|| Error::parse(key, at(key), "a parameter name"))?;
+            *params.fields()[i] = value
+                .parse::<f64>()
+                .map_err(|_| Error::parse(value, at(value), "a number"))?;
+        }
+        Ok(params)
+    }
+
+    /// Loads a parameter set from a file, see `EvalParams::parse`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<EvalParams, Error> {
+        let s = std::fs::read_to_string(path).map_err(|e| Error::IoError(e.to_string()))?;
+        EvalParams::parse(&s)
+    }
+
+    /// Writes the parameter set to a file in the format of `parse`.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path, self.to_string()).map_err(|e| Error::IoError(e.to_string()))
+    }
+}
+
+impl fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut p = *self;
+        for (key, value) in EvalParams::KEYS.iter().zip(p.fields()) {
+            writeln!(f, "{} = {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn params_round_trip() {
+    let p = EvalParams {
+        joker: 0.25,
+        point_penalty: 0.01,
+        ..EvalParams::default()
+    };
+    assert_eq!(EvalParams::parse(&p.to_string()).unwrap(), p);
+
+    let p = EvalParams::parse("# tuned\n\nsingle = 0.1\n  joker=2\n").unwrap();
+    assert_eq!(p.single, 0.1);
+    assert_eq!(p.joker, 2.0);
+    assert_eq!(p.meld_card, 1.0);
+
+    match EvalParams::parse("single = 0.1\nfoo = 1") {
+        Err(Error::ParseError { token, offset, .. }) => {
+            assert_eq!(token, "foo");
+            assert_eq!(offset, 13);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    match EvalParams::parse("single = 0.1\n  joker = abc") {
+        Err(Error::ParseError { token, offset, .. }) => {
+            assert_eq!(token, "abc");
+            assert_eq!(offset, 23);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
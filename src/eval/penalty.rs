@@ -0,0 +1,143 @@
+use crate::card::*;
+use crate::gamedef::*;
+use crate::DEAL_SIZE;
+
+use super::*;
+
+/// Number of turns per player after which an opponent is expected to be
+/// ready to go out, regardless of the size of their hand.
+pub const EXPECTED_ROUND_TURNS: u32 = 20;
+
+/// Largest probability that a single opponent goes out on their turn.
+const MAX_HAZARD: f64 = 0.5;
+
+/// Public information about the progress of a round, which determines
+/// how likely it is to get caught with cards in hand.
+#[derive(Clone, Debug, Default)]
+pub struct RoundState {
+    /// Number of turns every player has played so far.
+    pub turn: u32,
+    /// Number of cards in the hand of every opponent.
+    pub opponent_cards: Vec<usize>,
+}
+
+impl RoundState {
+    /// Estimated probability that an opponent with the given number of
+    /// cards goes out on their next turn. Opponents get closer to going
+    /// out as they lay off cards, and as the round goes on.
+    pub fn hazard(&self, cards: usize) -> f64 {
+        let readiness = 1.0 - (cards as f64 / (DEAL_SIZE + 1) as f64).min(1.0);
+        let progress = (self.turn as f64 / EXPECTED_ROUND_TURNS as f64).min(1.0);
+        MAX_HAZARD * readiness.max(progress)
+    }
+
+    /// Estimated probability that any opponent goes out before our next
+    /// turn.
+    pub fn p_opponent_out(&self) -> f64 {
+        let p_none: f64 = self
+            .opponent_cards
+            .iter()
+            .map(|&n| 1.0 - self.hazard(n))
+            .product();
+        1.0 - p_none
+    }
+}
+
+/// Penalty points of the cards that are not part of a meld in the optimal
+/// decomposition of the hand.
+pub fn unmelded_points(h: &Hand) -> u32 {
+    let sorted = sorted_hand(h);
+    let points = optimal_decomposition(&sorted)
+        .iter()
+        .filter(|g| !g.is_meld())
+        .flat_map(|g| g.iter())
+        .map(|c| c.points())
+        .sum();
+    points
+}
+
+/// Expected penalty of the hand if an opponent goes out before our next
+/// turn.
+pub fn expected_penalty(h: &Hand, round: &RoundState) -> f64 {
+    round.p_opponent_out() * unmelded_points(h) as f64
+}
+
+/// Same as `score_hand`, but subtracts the expected penalty of the hand
+/// weighted by `params.penalty_risk`.
+pub fn score_hand_in_round(h: &Hand, d: &Deck, params: &EvalParams, round: &RoundState) -> f64 {
+    score_hand(h, d, params) - params.penalty_risk * expected_penalty(h, round)
+}
+
+/// Same as `best_discard`, but ranks the remaining hands by
+/// `score_hand_in_round`. Late in the round this prefers dumping high
+/// cards over keeping them for a meld.
+pub fn best_discard_in_round(
+    h: &Hand,
+    d: &Deck,
+    params: &EvalParams,
+    round: &RoundState,
+) -> Option<(Card, f64)> {
+    best_discard_by(h, |_, rest| {
+        Some(score_hand_in_round(rest, d, params, round))
+    })
+}
+
+#[test]
+fn opponent_out_probability() {
+    let early = RoundState {
+        turn: 0,
+        opponent_cards: vec![DEAL_SIZE + 1, DEAL_SIZE],
+    };
+    let late = RoundState {
+        turn: EXPECTED_ROUND_TURNS,
+        ..early.clone()
+    };
+    let close = RoundState {
+        turn: 0,
+        opponent_cards: vec![2],
+    };
+    assert!(early.p_opponent_out() < 0.05);
+    assert!((late.p_opponent_out() - 0.75).abs() < 1e-9);
+    assert!(close.p_opponent_out() > early.p_opponent_out());
+}
+
+#[test]
+fn dumps_high_cards_late() {
+    // keep the quasi-meld Qh Kh early, but not when someone may go out
+    let h = Hand::parse_sorted("5c 6c 7c 3d Qh Kh").unwrap();
+    let d = Deck::from_known(&h, &[], &[]).unwrap();
+    let params = EvalParams {
+        quasi_street: 0.2,
+        ..EvalParams::default()
+    };
+    let early = RoundState {
+        turn: 0,
+        opponent_cards: vec![DEAL_SIZE, DEAL_SIZE],
+    };
+    let late = RoundState {
+        turn: EXPECTED_ROUND_TURNS,
+        opponent_cards: vec![3, 3],
+    };
+    let (c, _) = best_discard_in_round(&h, &d, &params, &early).unwrap();
+    assert_eq!(c, Card::parse("3d").unwrap());
+    // Qh and Kh are worth the same number of points
+    let (c, _) = best_discard_in_round(&h, &d, &params, &late).unwrap();
+    assert!(c.n == 12 || c.n == 13);
+}
+
+#[test]
+fn unmelded_points_of_unsorted_hand() {
+    let h = Hand::parse("7c 5c 6c Kd").unwrap();
+    assert_eq!(unmelded_points(&h), 10);
+    let d = Deck::from_known(&h, &[], &[]).unwrap();
+    let params = EvalParams::default();
+    let round = RoundState {
+        turn: 0,
+        opponent_cards: vec![DEAL_SIZE],
+    };
+    let sorted = Hand::parse_sorted("7c 5c 6c Kd").unwrap();
+    assert_eq!(
+        score_hand_in_round(&h, &d, &params, &round),
+        score_hand_in_round(&sorted, &d, &params, &round)
+    );
+}
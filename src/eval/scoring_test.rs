@@ -8,10 +8,73 @@ use super::super::*;
 fn assert_higher_score(higher: &str, lower: &str, d: &Deck) {
     let h1 = Hand::parse(higher).unwrap();
     let h2 = Hand::parse(lower).unwrap();
-    let result = score_hand(&h1, d) > score_hand(&h2, d);
+    let params = EvalParams::default();
+    let result = score_hand(&h1, d, &params) > score_hand(&h2, d, &params);
     assert!(
         result,
         "\n\tIncorrect scoring:\n\t\thigher:\t{:?}\n\t\tlower: \t{:?}\n",
         h2, h1
     );
 }
+
+#[test]
+fn safe_discard_avoids_layoff() {
+    // both 9s and Kd are worthless to us, but 9s extends a table meld
+    let h = Hand::parse("Ac 2c 3c 9s Kd").unwrap();
+    let d = Deck::from_known(&h, &[], &[]).unwrap();
+    let table = Table {
+        melds: vec![Hand::parse("6s 7s 8s").unwrap()],
+    };
+    let next = OpponentModel::new(&d, 14, BehaviorModel::default());
+    let (c, _) = safe_discard(&h, &d, &table, &next, &EvalParams::default()).unwrap();
+    assert_eq!(c, Card::parse("Kd").unwrap());
+}
+
+#[test]
+fn freeing_card_is_valued() {
+    let table = Table {
+        melds: vec![Hand::parse("5c X 7c").unwrap()],
+    };
+    let with = Hand::parse_sorted("Ac 2c 3c 6c Kd").unwrap();
+    let without = Hand::parse_sorted("Ac 2c 3c 6h Kd").unwrap();
+    assert_eq!(freeing_cards(&with, &table), 1);
+    assert_eq!(freeing_cards(&Hand::parse("6h 9d").unwrap(), &table), 0);
+
+    let d = Deck::new();
+    let params = EvalParams::default();
+    assert_eq!(
+        score_hand(&with, &d, &params),
+        score_hand(&without, &d, &params)
+    );
+    assert!(
+        score_hand_on_table(&with, &d, &table, &params)
+            > score_hand_on_table(&without, &d, &table, &params)
+    );
+}
+
+#[test]
+fn cached_scores_match() {
+    let params = EvalParams::default();
+    let d = Deck::new();
+    let mut cache = SearchCache::new(64);
+    let h = Hand::parse_sorted("Ac 2c 3c 5h 5d 5s 9d").unwrap();
+    let score = score_hand(&h, &d, &params);
+    assert_eq!(score_hand_cached(&h, &d, &params, &mut cache), score);
+    assert_eq!(score_hand_cached(&h, &d, &params, &mut cache), score);
+
+    // the order of the cards doesn't matter
+    let shuffled = Hand::parse("9d 5s 3c 5h Ac 5d 2c").unwrap();
+    assert_eq!(score_hand(&shuffled, &d, &params), score);
+    let mut fresh = SearchCache::new(64);
+    assert_eq!(score_hand_cached(&shuffled, &d, &params, &mut fresh), score);
+
+    // the same hand with clubs and diamonds swapped
+    let mirrored = Hand::parse_sorted("Ad 2d 3d 5h 5c 5s 9c").unwrap();
+    // equal up to the order in which the odds are summed
+    assert!((score_hand(&mirrored, &d, &params) - score).abs() < 1e-12);
+    assert_eq!(score_hand_cached(&mirrored, &d, &params, &mut cache), score);
+
+    let p = optimal_decomposition_cached(&h, &mut cache);
+    let q = optimal_decomposition_cached(&h, &mut cache);
+    assert_eq!(format!("{:?}", p), format!("{:?}", q));
+}
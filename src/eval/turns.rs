@@ -0,0 +1,178 @@
+use core::fmt;
+
+use crate::gamedef::*;
+
+use super::*;
+
+/// Configuration of a Monte Carlo simulation over the unseen pool.
+#[derive(Copy, Clone, Debug)]
+pub struct SimConfig {
+    /// Number of simulated draw sequences.
+    pub samples: u32,
+    /// Maximum number of turns per simulation.
+    pub horizon: u32,
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            samples: 1000,
+            horizon: 20,
+            seed: 0,
+        }
+    }
+}
+
+/// Distribution of the number of turns it takes to go out.
+#[derive(Clone, Debug)]
+pub struct TurnsDistribution {
+    /// `counts[k]` is the number of simulations that went out on turn k + 1.
+    pub counts: Vec<u32>,
+    /// Number of simulations that didn't go out within the horizon.
+    pub censored: u32,
+}
+
+impl TurnsDistribution {
+    pub fn samples(&self) -> u32 {
+        self.counts.iter().sum::<u32>() + self.censored
+    }
+
+    /// Probability of going out within k turns.
+    pub fn p_within(&self, k: u32) -> f64 {
+        let hits: u32 = self.counts.iter().take(k as usize).sum();
+        hits as f64 / self.samples().max(1) as f64
+    }
+
+    /// Expected number of turns to go out. Simulations that didn't go out
+    /// count as horizon + 1 turns, so this is a lower bound.
+    pub fn mean(&self) -> f64 {
+        let censored_turns = (self.counts.len() + 1) as f64 * self.censored as f64;
+        let turns: f64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(k, &n)| (k + 1) as f64 * n as f64)
+            .sum();
+        (turns + censored_turns) / self.samples().max(1) as f64
+    }
+
+    /// Smallest number of turns within which at least half of the
+    /// simulations went out, if any.
+    pub fn median(&self) -> Option<u32> {
+        (1..=self.counts.len() as u32).find(|&k| self.p_within(k) >= 0.5)
+    }
+}
+
+impl fmt::Display for TurnsDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let samples = self.samples().max(1) as f64;
+        for (k, &n) in self.counts.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3}: {:>5.1}%  (cumulative {:>5.1}%)",
+                k + 1,
+                100.0 * n as f64 / samples,
+                100.0 * self.p_within(k as u32 + 1)
+            )?;
+        }
+        write!(f, "  >: {:>5.1}%", 100.0 * self.censored as f64 / samples)
+    }
+}
+
+/// Returns true if the hand can be laid out completely, except for
+/// exactly one card that is discarded, see `going_out`.
+pub fn can_go_out(h: &Hand) -> bool {
+    going_out(h).is_some()
+}
+
+/// Estimates how many turns it takes to go out from the given hand by
+/// simulating draws from the deck, spread over all available threads.
+/// Each turn a card is drawn and, unless the hand can go out, the best
+/// discard according to `score_hand` is made.
+pub fn turns_to_go_out(
+    h: &Hand,
+    d: &Deck,
+    params: &EvalParams,
+    cfg: &SimConfig,
+) -> TurnsDistribution {
+    let results = rollouts(cfg.samples, cfg.seed, default_threads(), |rng| {
+        simulate(h, d, params, cfg.horizon, rng)
+    });
+    let mut dist = TurnsDistribution {
+        counts: vec![0; cfg.horizon as usize],
+        censored: 0,
+    };
+    for result in results {
+        match result {
+            Some(turn) => dist.counts[turn as usize - 1] += 1,
+            None => dist.censored += 1,
+        }
+    }
+    dist
+}
+
+/// Plays out a single draw sequence and returns the turn on which the
+/// hand went out.
+fn simulate(
+    h: &Hand,
+    d: &Deck,
+    params: &EvalParams,
+    horizon: u32,
+    rng: &fastrand::Rng,
+) -> Option<u32> {
+    let mut hand = h.clone();
+    let mut deck = d.clone();
+    for turn in 1..=horizon {
+        let c = deck.remove_random_with(rng).ok()?;
+        if hand.try_push(c).is_err() {
+            return None;
+        }
+        hand.sort_unstable();
+        if can_go_out(&hand) {
+            return Some(turn);
+        }
+        let (discard, _) = best_discard(&hand, &deck, params)?;
+        let i = hand.iter().position(|&x| x == discard).unwrap();
+        hand.remove(i);
+    }
+    None
+}
+
+#[test]
+fn go_out_detection() {
+    assert!(can_go_out(
+        &Hand::parse_sorted("Ac 2c 3c 5h 5d 5s Kd").unwrap()
+    ));
+    assert!(!can_go_out(
+        &Hand::parse_sorted("Ac 2c 3c 5h 5d 9s Kd").unwrap()
+    ));
+}
+
+#[test]
+fn closer_hands_go_out_sooner() {
+    let cfg = SimConfig {
+        samples: 40,
+        horizon: 10,
+        seed: 7,
+    };
+    let params = EvalParams::default();
+    let near = Hand::parse_sorted("Ac 2c 3c 5h 5d 5s 9d 10d").unwrap();
+    let far = Hand::parse_sorted("Ac 4d 7h 10s 2s 5c 8d Jh").unwrap();
+    let near_dist = turns_to_go_out(
+        &near,
+        &Deck::from_known(&near, &[], &[]).unwrap(),
+        &params,
+        &cfg,
+    );
+    let far_dist = turns_to_go_out(
+        &far,
+        &Deck::from_known(&far, &[], &[]).unwrap(),
+        &params,
+        &cfg,
+    );
+
+    assert_eq!(near_dist.samples(), 40);
+    assert!(near_dist.mean() < far_dist.mean());
+    assert!(near_dist.p_within(10) > far_dist.p_within(10));
+}
@@ -0,0 +1,280 @@
+// This module contains a simplified model of a full round between bots,
+// used for self-play. Every bot draws, optionally takes the top of the
+// discard pile, and discards according to its own evaluation weights.
+// A round ends as soon as one bot can lay out its whole hand.
+use crate::*;
+
+/// Number of cards every player is dealt.
+pub const DEAL_SIZE: usize = 14;
+
+/// Outcome of a single simulated round.
+#[derive(Clone, Debug)]
+pub struct RoundResult {
+    /// Index of the player that went out, if any.
+    pub winner: Option<usize>,
+    /// Number of turns played, counted over all players.
+    pub turns: u32,
+    /// Penalty points of every player's hand at the end of the round.
+    pub points: Vec<u32>,
+    /// For every player, the first of their own turns, counted from zero,
+    /// on which their hand was worth opening, see `best_opening`.
+    pub opening_turns: Vec<Option<u32>>,
+}
+
+/// Plays a round between the given bots, where the first bot is dealt
+/// an additional card and starts by discarding. The round is cut off as
+/// a draw after `max_turns` turns or when the stock runs out. Fails if the
+/// round can't be dealt, see `deal`.
+pub fn play_round(
+    bots: &[EvalParams],
+    rules: &RuleSet,
+    seed: u64,
+    max_turns: u32,
+) -> Result<RoundResult, Error> {
+    let Deal {
+        mut hands,
+        mut stock,
+    } = deal(bots.len(), rules, seed)?;
+
+    // every player's view of the cards that are still unseen
+    let mut decks: Vec<Deck> = hands
+        .iter()
+        .map(|h| Deck::from_known(h, &[], &[]).unwrap())
+        .collect();
+    // cards taken from the discard pile, which all players have seen
+    let mut picked: Vec<Vec<Card>> = vec![Vec::new(); bots.len()];
+    let mut discard: Vec<Card> = Vec::new();
+    let mut opening_turns = vec![None; bots.len()];
+    let mut turns = 0;
+    let mut winner = None;
+
+    'round: for turn in 0..max_turns {
+        let p = turn as usize % bots.len();
+        let params = &bots[p];
+        let round = RoundState {
+            turn: turn / bots.len() as u32,
+            opponent_cards: (0..bots.len())
+                .filter(|&o| o != p)
+                .map(|o| hands[o].len())
+                .collect(),
+        };
+        let hand = &mut hands[p];
+        turns = turn + 1;
+
+        if turn > 0 {
+            let take = match discard.last() {
+                Some(&top) => takes_discard(hand, top, &decks[p], params),
+                None => false,
+            };
+            let c = match take {
+                true => {
+                    let c = discard.pop().unwrap();
+                    picked[p].push(c);
+                    c
+                }
+                false => match stock.pop() {
+                    Some(c) => {
+                        decks[p].remove(c).unwrap();
+                        c
+                    }
+                    None => break 'round,
+                },
+            };
+            hand.push(c);
+            hand.sort_unstable();
+        }
+        if opening_turns[p].is_none() && best_opening(hand, rules).is_some() {
+            opening_turns[p] = Some(round.turn);
+        }
+        if can_go_out(hand) {
+            winner = Some(p);
+            break 'round;
+        }
+        let c = recommend_discard(hand, &decks[p], params, &round).unwrap();
+        let i = hand.iter().position(|&x| x == c).unwrap();
+        hand.remove(i);
+        discard.push(c);
+        match picked[p].iter().position(|&x| x == c) {
+            Some(i) => {
+                picked[p].swap_remove(i);
+            }
+            None => {
+                for (other, d) in decks.iter_mut().enumerate() {
+                    if other != p {
+                        d.remove(c).unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(RoundResult {
+        winner,
+        turns,
+        points: hands
+            .iter()
+            .enumerate()
+            .map(|(i, h)| match winner == Some(i) {
+                true => 0,
+                false => h.iter().map(|c| c.points()).sum(),
+            })
+            .collect(),
+        opening_turns,
+    })
+}
+
+/// The opening hands of a round and the remaining stock.
+#[derive(Clone, Debug)]
+pub struct Deal {
+    /// Hands in the order of play. The first hand has one card more.
+    pub hands: Vec<Hand>,
+    /// Cards left to draw, where the last card is on top.
+    pub stock: Vec<Card>,
+}
+
+/// Deals a round for the given number of players from a shuffled deck.
+/// Every hand is sorted.
+pub fn deal(players: usize, rules: &RuleSet, seed: u64) -> Result<Deal, Error> {
+    deal_with_known(players, rules, seed, &[])
+}
+
+/// Same as `deal`, but the i-th hand contains the cards of `known[i]`.
+/// Only the rest of the cards is random, so our own hand can be fixed
+/// while the hands of the opponents and the stock are sampled. Fails for
+/// an invalid player count or deal size, or if the known cards don't fit
+/// into the hands or exceed the deck.
+pub fn deal_with_known(
+    players: usize,
+    rules: &RuleSet,
+    seed: u64,
+    known: &[Hand],
+) -> Result<Deal, Error> {
+    if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&players) || known.len() > players {
+        return Err(Error::PlayerCount(players.max(known.len())));
+    }
+    // even the largest table leaves cards in the stock
+    if rules.deal_size + 1 > MAX_HAND_SIZE {
+        return Err(Error::DealSize(rules.deal_size));
+    }
+    let all_known: Vec<Card> = known.iter().flatten().copied().collect();
+    validate_cards(&Hand::new(), &all_known, &[])?;
+
+    let rng = fastrand::Rng::with_seed(seed);
+    let mut stock = shuffled_deck(&rng);
+    for c in all_known.iter() {
+        let i = stock.iter().position(|x| x == c).unwrap();
+        stock.remove(i);
+    }
+
+    let mut hands = Vec::with_capacity(players);
+    for i in 0..players {
+        let size = match i {
+            0 => rules.deal_size + 1,
+            _ => rules.deal_size,
+        };
+        let mut hand = known.get(i).cloned().unwrap_or_default();
+        if let Some(&c) = hand.get(size) {
+            return Err(Error::CapacityError(c));
+        }
+        let missing = size - hand.len();
+        hand.extend(stock.drain(stock.len() - missing..));
+        hand.sort_unstable();
+        hands.push(hand);
+    }
+    Ok(Deal { hands, stock })
+}
+
+/// Returns all 108 cards of a full deck in random order.
+pub fn shuffled_deck(rng: &fastrand::Rng) -> Vec<Card> {
+    let mut cards: Vec<Card> = Card::all()
+        .flat_map(|c| std::iter::repeat_n(c, copies_in_deck(c) as usize))
+        .collect();
+    rng.shuffle(&mut cards);
+    cards
+}
+
+/// A bot takes the top of the discard pile if that improves its hand.
+fn takes_discard(h: &Hand, top: Card, d: &Deck, params: &EvalParams) -> bool {
+    let mut with_top = h.clone();
+    if with_top.try_push(top).is_err() {
+        return false;
+    }
+    with_top.sort_unstable();
+    if can_go_out(&with_top) {
+        return true;
+    }
+    match best_discard(&with_top, d, params) {
+        Some((c, score)) => c != top && score > score_hand(h, d, params),
+        None => false,
+    }
+}
+
+#[test]
+fn rounds_are_reproducible() {
+    let bots = [EvalParams::default(), EvalParams::default()];
+    let rules = RuleSet::default();
+    let a = play_round(&bots, &rules, 42, 40).unwrap();
+    let b = play_round(&bots, &rules, 42, 40).unwrap();
+    assert_eq!(a.winner, b.winner);
+    assert_eq!(a.turns, b.turns);
+    assert_eq!(a.points, b.points);
+    assert_eq!(a.opening_turns, b.opening_turns);
+    assert_eq!(a.points.len(), 2);
+}
+
+#[test]
+fn full_shuffled_deck() {
+    let cards = shuffled_deck(&fastrand::Rng::with_seed(1));
+    assert_eq!(cards.len(), 108);
+    let h: Hand = cards.iter().take(MAX_HAND_SIZE).copied().collect();
+    assert!(validate_cards(&h, &cards[MAX_HAND_SIZE..], &[]).is_ok());
+}
+
+#[test]
+fn deal_sizes() {
+    let rules = RuleSet::default();
+    for players in MIN_PLAYERS..=MAX_PLAYERS {
+        let d = deal(players, &rules, 9).unwrap();
+        assert_eq!(d.hands.len(), players);
+        assert_eq!(d.hands[0].len(), DEAL_SIZE + 1);
+        assert!(d.hands[1..].iter().all(|h| h.len() == DEAL_SIZE));
+        assert_eq!(d.stock.len(), 108 - players * DEAL_SIZE - 1);
+    }
+    assert!(deal(1, &rules, 9).is_err());
+    assert!(deal(MAX_PLAYERS + 1, &rules, 9).is_err());
+    assert!(play_round(&[EvalParams::default()], &rules, 9, 10).is_err());
+    let huge = RuleSet {
+        deal_size: MAX_HAND_SIZE,
+        ..rules
+    };
+    assert!(matches!(deal(2, &huge, 9), Err(Error::DealSize(_))));
+    assert_eq!(
+        deal(3, &rules, 9).unwrap().stock,
+        deal(3, &rules, 9).unwrap().stock
+    );
+}
+
+#[test]
+fn deal_known_cards() {
+    let rules = RuleSet::default();
+    let ours = Hand::parse_sorted("X X Ac Ac 2c 3c 4c 5c 6c 7c 8c 9c 10c Jc").unwrap();
+    let known = [Hand::new(), ours.clone()];
+    let a = deal_with_known(3, &rules, 1, &known).unwrap();
+    let b = deal_with_known(3, &rules, 2, &known).unwrap();
+    assert_eq!(a.hands[1], ours);
+    assert_ne!(a.hands[0], b.hands[0]);
+
+    // all dealt cards together form a full deck
+    let all: Vec<Card> = a
+        .hands
+        .iter()
+        .flatten()
+        .copied()
+        .chain(a.stock.iter().copied())
+        .collect();
+    assert_eq!(all.len(), 108);
+    assert!(validate_cards(&Hand::new(), &all, &[]).is_ok());
+
+    let too_many = [Hand::parse("X X X").unwrap(), Hand::parse("X X").unwrap()];
+    assert!(deal_with_known(2, &rules, 1, &too_many).is_err());
+}
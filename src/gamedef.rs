@@ -17,12 +17,17 @@ pub trait HandUtil {
     /// Fills the Card array with n cards.
     fn fill(&mut self, n: u32);
 
-    /// Parses a space-separated string and generates a hand from it.
-    /// Fails if the syntax/card symbols are incorrect.
+    /// Parses a string of cards separated by whitespace and/or commas and
+    /// generates a hand from it. Fails if a card symbol is incorrect.
     fn parse(s: &str) -> Result<Hand, Error>;
 
     /// Parses a Hand string and returns a sorted collection of card.
     fn parse_sorted(s: &str) -> Result<Hand, Error>;
+
+    /// Returns a wrapper that formats the hand in the notation accepted by
+    /// `parse`. As `Hand` is an alias of a foreign type, this takes the place
+    /// of a `Display` implementation, just like `parse` replaces `FromStr`.
+    fn display(&self) -> DisplayCards<'_>;
 }
 
 impl HandUtil for Hand {
@@ -34,9 +39,8 @@ impl HandUtil for Hand {
 
     fn parse(s: &str) -> Result<Hand, Error> {
         let mut h = Hand::new();
-        let split = s.split(' ');
-        for chunk in split {
-            let card = Card::parse(chunk)?;
+        for (offset, token) in tokenize(s) {
+            let card = Card::parse_at(token, offset)?;
             if h.try_push(card).is_err() {
                 return Err(Error::CapacityError(card));
             }
@@ -49,10 +53,62 @@ impl HandUtil for Hand {
         h.sort_unstable();
         Ok(h)
     }
+
+    fn display(&self) -> DisplayCards<'_> {
+        DisplayCards(self)
+    }
+}
+
+/// Parses any number of cards in the notation of `HandUtil::parse`, e.g.
+/// all cards seen during a round.
+pub fn parse_cards(s: &str) -> Result<Vec<Card>, Error> {
+    tokenize(s)
+        .into_iter()
+        .map(|(offset, token)| Card::parse_at(token, offset))
+        .collect()
+}
+
+/// Splits a string into tokens separated by whitespace or commas.
+/// Returns every non-empty token together with its byte offset.
+pub(crate) fn tokenize(s: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        if c.is_whitespace() || c == ',' {
+            if let Some(b) = start.take() {
+                tokens.push((b, &s[b..i]));
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(b) = start {
+        tokens.push((b, &s[b..]));
+    }
+    tokens
+}
+
+/// Formats a sequence of cards separated by spaces, e.g. "Ac 2c X".
+/// The alternate style (`{:#}`) prints "A♣ 2♣ 🃏".
+///
+/// It holds that `Hand::parse(&h.display().to_string()) == Ok(h)`.
+pub struct DisplayCards<'a>(pub &'a [Card]);
+
+impl fmt::Display for DisplayCards<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, c) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            fmt::Display::fmt(c, f)?;
+        }
+        Ok(())
+    }
 }
 
 /// A Deck is the primary source of cards, containing all unique cards n-times,
 /// where n is the number of duplicates. For this version of Rummy, n = 2.
+#[derive(Clone)]
 pub struct Deck {
     cards: [(Card, u32); UNIQUE_CARDS],
 }
@@ -60,16 +116,30 @@ pub struct Deck {
 impl Deck {
     /// Creates a full deck (108 cards, 2 duplicates + 4 jokers)
     pub fn new() -> Self {
-        let mut idx = 0;
-        let mut arr = [(JOKER_CARD, 2); UNIQUE_CARDS].map(|mut _c| {
-            let n = Value::new(((idx / 4) % MAX_CARD_VAL) + 1);
-            let suit = Suit::from_int(1 << (idx % 4)).unwrap();
-            idx += 1;
-            (Card { n, suit }, DUPLICATE_COUNT)
-        });
-        // Add all jokers at the end
-        arr[arr.len() - 1] = (JOKER_CARD, JOKER_TOTAL);
-        Deck { cards: arr }
+        let mut cards = [(JOKER_CARD, 0); UNIQUE_CARDS];
+        for c in Card::all() {
+            cards[c.to_index()] = (c, copies_in_deck(c));
+        }
+        Deck { cards }
+    }
+
+    /// Creates the remainder of a full deck after taking out the given hand,
+    /// the cards on the table and the discard pile. Fails if the cards are
+    /// impossible to have, see `validate_cards`.
+    pub fn from_known(hand: &Hand, table: &[Card], discard: &[Card]) -> Result<Deck, Error> {
+        validate_cards(hand, table, discard)?;
+        let mut d = Deck::new();
+        for &c in hand.iter().chain(table).chain(discard) {
+            d.remove(c)?;
+        }
+        Ok(d)
+    }
+
+    /// Creates the remainder of a full deck after taking out all cards that
+    /// have been seen, in any order. Fails if more copies of a card were
+    /// seen than exist.
+    pub fn from_seen(seen: &[Card]) -> Result<Deck, Error> {
+        Deck::from_known(&Hand::new(), seen, &[])
     }
 
     /// Removes the given card from the Deck if it's contained.
@@ -93,9 +163,247 @@ impl Deck {
         }
     }
 
+    /// Draws a card with the given random generator, where every remaining
+    /// copy is equally likely. Fails if the deck is empty.
+    pub fn remove_random_with(&mut self, rng: &fastrand::Rng) -> Result<Card, Error> {
+        let total = self.remaining();
+        if total == 0 {
+            return Err(Error::CardNotContained(JOKER_CARD));
+        }
+        let mut k = rng.u32(..total);
+        for i in 0..UNIQUE_CARDS {
+            let (c, n) = self.cards[i];
+            if k < n {
+                self.cards[i].1 -= 1;
+                return Ok(c);
+            }
+            k -= n;
+        }
+        unreachable!()
+    }
+
+    /// Iterates over all unique cards together with the number of
+    /// copies left in the deck.
+    pub fn iter(&self) -> impl Iterator<Item = (Card, u32)> + '_ {
+        self.cards.iter().copied()
+    }
+
+    /// Total number of cards left in the deck.
+    pub fn remaining(&self) -> u32 {
+        self.cards.iter().map(|&(_, n)| n).sum()
+    }
+
+    /// Number of cards of the given suit left in the deck.
+    pub fn remaining_suit(&self, suit: Suit) -> u32 {
+        self.iter()
+            .filter(|(c, _)| c.suit == suit)
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    /// Number of regular cards of the given value left in the deck.
+    pub fn remaining_value(&self, n: Value) -> u32 {
+        self.iter()
+            .filter(|(c, _)| c.n == n && c.suit != Suit::Joker)
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    pub fn jokers_left(&self) -> u32 {
+        self[JOKER_CARD]
+    }
+
+    /// Number of cards left in the deck that are one of the targets.
+    /// Duplicate targets are counted once.
+    pub fn outs(&self, targets: &[Card]) -> u32 {
+        Card::all()
+            .filter(|c| targets.contains(c))
+            .map(|c| self[c])
+            .sum()
+    }
+
+    /// Probability that at least one of the targets is among the next `k`
+    /// cards drawn from the deck.
+    pub fn p_any_drawn(&self, targets: &[Card], k: u32) -> f64 {
+        let total = self.remaining() as f64;
+        let others = total - self.outs(targets) as f64;
+        let mut p_none = 1.0;
+        for i in 0..k.min(self.remaining()) {
+            p_none *= ((others - i as f64) / (total - i as f64)).max(0.0);
+        }
+        1.0 - p_none
+    }
+
+    /// Expected number of targets among the next `k` cards drawn from the
+    /// deck.
+    pub fn expected_outs(&self, targets: &[Card], k: u32) -> f64 {
+        match self.remaining() {
+            0 => 0.0,
+            total => k.min(total) as f64 * self.outs(targets) as f64 / total as f64,
+        }
+    }
+
+    /// Probability that the next card drawn from the deck is `card`.
     pub fn odds_to_draw(&self, card: Card) -> f64 {
-        let cards_left = self.cards[card.to_index()].1 as f64;
-        cards_left / 104.0
+        match self.remaining() {
+            0 => 0.0,
+            total => self.cards[card.to_index()].1 as f64 / total as f64,
+        }
+    }
+}
+
+/// Number of copies of the given card in a full deck.
+pub fn copies_in_deck(c: Card) -> u32 {
+    match c.suit {
+        Suit::Joker => JOKER_TOTAL,
+        _ => DUPLICATE_COUNT,
+    }
+}
+
+/// Checks a hand, together with the cards on the table and the discard
+/// pile, against the multiplicities of a full deck. Returns an
+/// `Error::Overdrawn` listing every card that occurs too often.
+pub fn validate_cards(hand: &Hand, table: &[Card], discard: &[Card]) -> Result<(), Error> {
+    let mut counts: Vec<(Card, u32)> = Vec::new();
+    for &c in hand.iter().chain(table).chain(discard) {
+        match counts.iter_mut().find(|(x, _)| *x == c) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((c, 1)),
+        }
+    }
+    counts.retain(|&(c, n)| n > copies_in_deck(c));
+    match counts.is_empty() {
+        true => Ok(()),
+        false => Err(Error::Overdrawn(counts)),
+    }
+}
+
+/// The melds that have been laid out on the table by all players.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    pub melds: Vec<Hand>,
+}
+
+impl Table {
+    /// Iterates over all cards on the table.
+    pub fn cards(&self) -> impl Iterator<Item = Card> + '_ {
+        self.melds.iter().flat_map(|m| m.iter().copied())
+    }
+
+    /// Returns true if the given card can be laid off onto any meld.
+    pub fn accepts(&self, c: Card) -> bool {
+        self.melds.iter().any(|m| lay_off_index(m, c).is_some())
+    }
+
+    /// Returns true if the card can be laid off onto the given meld.
+    pub fn meld_accepts(&self, meld: usize, c: Card) -> bool {
+        self.melds
+            .get(meld)
+            .is_some_and(|m| lay_off_index(m, c).is_some())
+    }
+
+    /// Indices of the melds in which the card can take the place of a
+    /// joker, see `exchange_joker`.
+    pub fn joker_exchanges(&self, c: Card) -> Vec<usize> {
+        (0..self.melds.len())
+            .filter(|&i| freed_joker(&self.melds[i], c).is_some())
+            .collect()
+    }
+
+    /// Puts the card in place of the joker of the given meld that stands
+    /// in for it, and returns the joker.
+    pub fn exchange_joker(&mut self, meld: usize, c: Card) -> Result<Card, Error> {
+        let m = self.melds.get_mut(meld).ok_or(Error::GroupIndexError)?;
+        let i = freed_joker(m, c).ok_or(Error::IllegalMove(
+            "the card can't take the place of a joker",
+        ))?;
+        Ok(std::mem::replace(&mut m[i], c))
+    }
+
+    /// Lays off the card onto the first meld that accepts it. Returns
+    /// false if no meld does.
+    pub fn lay_off(&mut self, c: Card) -> bool {
+        match (0..self.melds.len()).find(|&i| self.meld_accepts(i, c)) {
+            Some(i) => self.lay_off_onto(i, c),
+            None => false,
+        }
+    }
+
+    /// Lays off the card onto the given meld, at the end of a run it
+    /// extends. Returns false if the meld doesn't accept it.
+    pub fn lay_off_onto(&mut self, meld: usize, c: Card) -> bool {
+        let m = match self.melds.get_mut(meld) {
+            Some(m) => m,
+            None => return false,
+        };
+        match lay_off_index(m, c) {
+            Some(i) => m.try_insert(i, c).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// The card every position of a run stands for, given that the run is
+/// laid out in order. Returns `None` for sets and for runs that aren't in
+/// order.
+fn run_slots(m: &Hand) -> Option<Hand> {
+    let suit = match validate_meld(m.iter()) {
+        Ok(MeldKind::Run { suit, .. }) => suit,
+        _ => return None,
+    };
+    let j = m.iter().position(|c| c.suit != Suit::Joker)?;
+    let first = m[j].n.to_int() as usize;
+    if first <= j || first - j + m.len() - 1 > MAX_CARD_VAL as usize {
+        return None;
+    }
+    let slots: Hand = (0..m.len())
+        .map(|i| Card {
+            n: Value::new((first - j + i) as u32),
+            suit,
+        })
+        .collect();
+    let in_order = m
+        .iter()
+        .zip(slots.iter())
+        .all(|(c, s)| c.suit == Suit::Joker || c == s);
+    Some(slots).filter(|_| in_order)
+}
+
+/// Position at which the card extends the meld, if it does. Cards extend
+/// a run at either end, and a joker extends it upwards unless it ends at
+/// the king.
+fn lay_off_index(m: &Hand, c: Card) -> Option<usize> {
+    if m.is_full() {
+        return None;
+    }
+    if let Ok(MeldKind::Set { .. }) = validate_meld(m.iter()) {
+        return Some(m.len()).filter(|_| validate_meld(m.iter().chain(Some(&c))).is_ok());
+    }
+    let slots = run_slots(m)?;
+    let (lo, hi) = (slots[0], slots[slots.len() - 1]);
+    match c.suit {
+        Suit::Joker if hi.next().is_some() => Some(m.len()),
+        Suit::Joker if lo.prev().is_some() => Some(0),
+        _ if hi.next() == Some(c) => Some(m.len()),
+        _ if lo.prev() == Some(c) => Some(0),
+        _ => None,
+    }
+}
+
+/// Position of the joker the card stands in for. In a set, that's any of
+/// the missing suits, and in a run the card at the joker's position.
+fn freed_joker(m: &Hand, c: Card) -> Option<usize> {
+    if c.suit == Suit::Joker {
+        return None;
+    }
+    let is_joker = |i: &usize| m[*i].suit == Suit::Joker;
+    match validate_meld(m.iter()) {
+        Ok(MeldKind::Set { n, .. }) if c.n == n && !m.contains(&c) => (0..m.len()).find(is_joker),
+        Ok(MeldKind::Run { .. }) => {
+            let slots = run_slots(m)?;
+            (0..m.len()).filter(is_joker).find(|&i| slots[i] == c)
+        }
+        _ => None,
     }
 }
 
@@ -117,3 +425,142 @@ impl fmt::Debug for Deck {
         write!(f, "{:?}", self.cards)
     }
 }
+
+#[test]
+fn validate_overdrawn() {
+    let h = Hand::parse("Ac Ac Ac X X X X X 5h").unwrap();
+    match validate_cards(&h, &[], &[]) {
+        Err(Error::Overdrawn(v)) => {
+            assert_eq!(v, vec![(Card::parse("Ac").unwrap(), 3), (JOKER_CARD, 5)]);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    // copies are counted across hand, table and discard pile
+    let h = Hand::parse("5h Kd").unwrap();
+    let five = Card::parse("5h").unwrap();
+    assert!(validate_cards(&h, &[five], &[]).is_ok());
+    assert!(validate_cards(&h, &[five], &[five]).is_err());
+}
+
+#[test]
+fn deck_from_known() {
+    let h = Hand::parse("5h Kd X").unwrap();
+    let d = Deck::from_known(&h, &[Card::parse("5h").unwrap()], &[]).unwrap();
+    assert_eq!(d[Card::parse("5h").unwrap()], 0);
+    assert_eq!(d[Card::parse("Kd").unwrap()], 1);
+    assert_eq!(d[JOKER_CARD], JOKER_TOTAL - 1);
+}
+
+#[test]
+fn full_deck() {
+    let d = Deck::new();
+    for (c, n) in d.iter() {
+        assert_eq!(d[c], n);
+        assert_eq!(n, copies_in_deck(c));
+    }
+    let total: u32 = d.iter().map(|(_, n)| n).sum();
+    assert_eq!(total, 13 * 4 * DUPLICATE_COUNT + JOKER_TOTAL);
+}
+
+#[test]
+fn deck_statistics() {
+    let seen = parse_cards("5h 5h Kd X 2c").unwrap();
+    let d = Deck::from_seen(&seen).unwrap();
+    assert_eq!(d.remaining(), 108 - 5);
+    assert_eq!(d.remaining_suit(Suit::Hearts), 26 - 2);
+    assert_eq!(d.remaining_value(Value::new(5)), 8 - 2);
+    assert_eq!(d.jokers_left(), JOKER_TOTAL - 1);
+    assert!(Deck::from_seen(&parse_cards("5h 5h 5h").unwrap()).is_err());
+    let many = parse_cards(&"Ac 2c 3c 4c 5c 6c 7c 8c ".repeat(2)).unwrap();
+    assert_eq!(Deck::from_seen(&many).unwrap().remaining(), 108 - 16);
+
+    let targets = Hand::parse("5h 4h 4h 6h").unwrap();
+    assert_eq!(d.outs(&targets), 4);
+    assert_eq!(d.p_any_drawn(&targets, 0), 0.0);
+    assert!((d.p_any_drawn(&targets, 1) - 4.0 / 103.0).abs() < 1e-12);
+    assert!((d.p_any_drawn(&targets, 2) - (1.0 - 99.0 / 103.0 * 98.0 / 102.0)).abs() < 1e-12);
+    assert!((d.expected_outs(&targets, 10) - 40.0 / 103.0).abs() < 1e-12);
+    assert_eq!(d.p_any_drawn(&targets, 200), 1.0);
+}
+
+#[test]
+fn table_accepts() {
+    let t = Table {
+        melds: vec![
+            Hand::parse("5c 6c 7c").unwrap(),
+            Hand::parse("Kd Kh Ks").unwrap(),
+        ],
+    };
+    assert!(t.accepts(Card::parse("8c").unwrap()));
+    assert!(t.accepts(Card::parse("4c").unwrap()));
+    assert!(t.accepts(Card::parse("Kc").unwrap()));
+    assert!(t.accepts(JOKER_CARD));
+    assert!(!t.accepts(Card::parse("8d").unwrap()));
+    assert!(!t.accepts(Card::parse("Kd").unwrap()));
+}
+
+#[test]
+fn joker_exchange() {
+    let mut t = Table {
+        melds: vec![
+            Hand::parse("5c X 7c").unwrap(),
+            Hand::parse("Kd Kh X").unwrap(),
+            Hand::parse("X Qs Ks").unwrap(),
+        ],
+    };
+    assert_eq!(t.joker_exchanges(Card::parse("6c").unwrap()), vec![0]);
+    assert_eq!(
+        t.joker_exchanges(Card::parse("8c").unwrap()),
+        Vec::<usize>::new()
+    );
+    assert_eq!(t.joker_exchanges(Card::parse("Ks").unwrap()), vec![1]);
+    assert_eq!(
+        t.joker_exchanges(Card::parse("Kd").unwrap()),
+        Vec::<usize>::new()
+    );
+    assert_eq!(t.joker_exchanges(Card::parse("Js").unwrap()), vec![2]);
+
+    assert_eq!(
+        t.exchange_joker(0, Card::parse("6c").unwrap()).unwrap(),
+        JOKER_CARD
+    );
+    assert_eq!(t.melds[0].display().to_string(), "5c 6c 7c");
+    assert!(t.exchange_joker(0, Card::parse("6c").unwrap()).is_err());
+}
+
+#[test]
+fn joker_exchange_by_position() {
+    let card = |s: &str| Card::parse(s).unwrap();
+    // the joker at the low end stands in for 4c
+    let mut t = Table {
+        melds: vec![Hand::parse("X 5c 6c").unwrap()],
+    };
+    assert!(t.joker_exchanges(card("7c")).is_empty());
+    assert_eq!(t.joker_exchanges(card("4c")), vec![0]);
+    assert_eq!(t.exchange_joker(0, card("4c")).unwrap(), JOKER_CARD);
+    assert_eq!(t.melds[0].display().to_string(), "4c 5c 6c");
+
+    // the joker in the gap stands in for 6c, the one at the end for 8c
+    let mut t = Table {
+        melds: vec![Hand::parse("5c X 7c X").unwrap()],
+    };
+    assert!(t.joker_exchanges(card("4c")).is_empty());
+    t.exchange_joker(0, card("8c")).unwrap();
+    assert_eq!(t.melds[0].display().to_string(), "5c X 7c 8c");
+    t.exchange_joker(0, card("6c")).unwrap();
+    assert_eq!(t.melds[0].display().to_string(), "5c 6c 7c 8c");
+}
+
+#[test]
+fn lay_off_keeps_order() {
+    let mut t = Table {
+        melds: vec![Hand::parse("X 5c 6c").unwrap()],
+    };
+    // 4c would take the place of the joker
+    assert!(!t.accepts(Card::parse("4c").unwrap()));
+    assert!(t.lay_off(Card::parse("3c").unwrap()));
+    assert!(t.lay_off(Card::parse("7c").unwrap()));
+    assert_eq!(t.melds[0].display().to_string(), "3c X 5c 6c 7c");
+    assert_eq!(t.joker_exchanges(Card::parse("4c").unwrap()), vec![0]);
+}
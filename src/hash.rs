@@ -0,0 +1,183 @@
+// This module contains Zobrist hashing of hands and decks, canonicalization
+// of hands under suit symmetry, and a bounded transposition table that is
+// shared by the search functions.
+use crate::*;
+
+/// Maximum number of copies of a single card, i.e. the number of jokers.
+const MAX_COPIES: usize = JOKER_TOTAL as usize;
+
+/// Random keys for every copy of every unique card.
+static ZOBRIST: [[u64; MAX_COPIES]; UNIQUE_CARDS] = zobrist_keys();
+
+const fn zobrist_keys() -> [[u64; MAX_COPIES]; UNIQUE_CARDS] {
+    let mut keys = [[0; MAX_COPIES]; UNIQUE_CARDS];
+    let mut state: u64 = 0x005e_ed0f_4e41_5245;
+    let mut i = 0;
+    while i < UNIQUE_CARDS {
+        let mut j = 0;
+        while j < MAX_COPIES {
+            // splitmix64
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            keys[i][j] = z ^ (z >> 31);
+            j += 1;
+        }
+        i += 1;
+    }
+    keys
+}
+
+/// Zobrist hash of the multiset of cards in a hand. Hands with the same
+/// cards in a different order have the same hash.
+pub fn hash_hand(h: &[Card]) -> u64 {
+    let mut counts = [0; UNIQUE_CARDS];
+    let mut hash = 0;
+    for c in h.iter() {
+        let i = c.to_index();
+        hash ^= ZOBRIST[i][counts[i] % MAX_COPIES];
+        counts[i] += 1;
+    }
+    hash
+}
+
+/// Zobrist hash of the cards left in a deck. It's rotated, so that it can
+/// be combined with the hash of a hand by xor.
+pub fn hash_deck(d: &Deck) -> u64 {
+    let mut hash: u64 = 0;
+    for (c, n) in d.iter() {
+        for key in ZOBRIST[c.to_index()].iter().take(n as usize) {
+            hash ^= key;
+        }
+    }
+    hash.rotate_left(32)
+}
+
+/// All 24 permutations of the four regular suits.
+fn suit_permutations() -> Vec<[Suit; 4]> {
+    let suits = Suit::standard().as_slice();
+    let mut perms = Vec::with_capacity(24);
+    for a in 0..4 {
+        for b in 0..4 {
+            for c in 0..4 {
+                for d in 0..4 {
+                    if a != b && a != c && a != d && b != c && b != d && c != d {
+                        perms.push([suits[a], suits[b], suits[c], suits[d]]);
+                    }
+                }
+            }
+        }
+    }
+    perms
+}
+
+fn permute(c: Card, perm: &[Suit; 4]) -> Card {
+    match c.suit {
+        Suit::Joker => c,
+        s => Card {
+            n: c.n,
+            suit: perm[s.index()],
+        },
+    }
+}
+
+/// Returns the canonical representative of the hand under all suit
+/// permutations that leave the deck unchanged. Evaluations that only
+/// depend on the hand and the deck are equal for a hand and its canonical
+/// form. The result is sorted.
+pub fn canonical_hand(h: &Hand, d: &Deck) -> Hand {
+    let mut best = h.clone();
+    best.sort_unstable();
+    for perm in suit_permutations() {
+        let symmetric = Card::all().all(|c| d[c] == d[permute(c, &perm)]);
+        if !symmetric {
+            continue;
+        }
+        let mut p: Hand = h.iter().map(|&c| permute(c, &perm)).collect();
+        p.sort_unstable();
+        if p.as_slice() < best.as_slice() {
+            best = p;
+        }
+    }
+    best
+}
+
+/// Hash of a hand together with the deck, canonicalized under suit
+/// symmetry where the deck allows it.
+pub fn hash_state(h: &Hand, d: &Deck) -> u64 {
+    hash_hand(&canonical_hand(h, d)) ^ hash_deck(d)
+}
+
+/// A bounded memo table indexed by hash. When two keys map to the same
+/// slot, the newer entry replaces the older one.
+#[derive(Clone, Debug)]
+pub struct TranspositionTable<V> {
+    slots: Vec<Option<(u64, V)>>,
+}
+
+impl<V: Clone> TranspositionTable<V> {
+    /// Creates a table with at least the given number of slots, rounded
+    /// up to a power of two.
+    pub fn new(capacity: usize) -> Self {
+        TranspositionTable {
+            slots: vec![None; capacity.max(1).next_power_of_two()],
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<&V> {
+        match &self.slots[self.slot(key)] {
+            Some((k, v)) if *k == key => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: u64, value: V) {
+        let i = self.slot(key);
+        self.slots[i] = Some((key, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|s| *s = None);
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key as usize) & (self.slots.len() - 1)
+    }
+}
+
+#[test]
+fn hash_is_order_independent() {
+    let a = Hand::parse("Ac 2c 3c Ac X").unwrap();
+    let b = Hand::parse("X Ac 3c Ac 2c").unwrap();
+    let c = Hand::parse("Ac 2c 3c X X").unwrap();
+    assert_eq!(hash_hand(&a), hash_hand(&b));
+    assert_ne!(hash_hand(&a), hash_hand(&c));
+
+    let mut d = Deck::new();
+    let full = hash_deck(&d);
+    d.remove(Card::parse("Ac").unwrap()).unwrap();
+    assert_ne!(hash_deck(&d), full);
+}
+
+#[test]
+fn suit_symmetry() {
+    let d = Deck::new();
+    let a = Hand::parse("5c 6c 7c Kh").unwrap();
+    let b = Hand::parse("5s 6s 7s Kd").unwrap();
+    assert_eq!(hash_state(&a, &d), hash_state(&b, &d));
+
+    // a deck that distinguishes clubs from spades breaks the symmetry
+    let mut d = Deck::new();
+    d.remove(Card::parse("8c").unwrap()).unwrap();
+    assert_ne!(hash_state(&a, &d), hash_state(&b, &d));
+}
+
+#[test]
+fn table_is_bounded() {
+    let mut t: TranspositionTable<f64> = TranspositionTable::new(3);
+    t.insert(1, 1.0);
+    t.insert(5, 5.0);
+    assert_eq!(t.get(1), None);
+    assert_eq!(t.get(5), Some(&5.0));
+}
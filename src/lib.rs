@@ -18,12 +18,28 @@ pub const UNIQUE_CARDS: usize = 53;
 pub const DUPLICATE_COUNT: u32 = 2;
 pub const JOKER_TOTAL: u32 = 4;
 
+mod analysis;
 mod card;
 mod error;
 mod eval;
+mod game;
 mod gamedef;
+mod hash;
+mod meld;
+mod opponent;
+mod rules;
+mod simulation;
+mod tune;
 
+pub use crate::analysis::*;
 pub use crate::card::*;
 pub use crate::error::Error;
 pub use crate::eval::*;
+pub use crate::game::*;
 pub use crate::gamedef::*;
+pub use crate::hash::*;
+pub use crate::meld::*;
+pub use crate::opponent::*;
+pub use crate::rules::*;
+pub use crate::simulation::*;
+pub use crate::tune::*;
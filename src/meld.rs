@@ -0,0 +1,220 @@
+// This module contains the rules for valid melds. Every other part of the
+// crate that needs to know whether a group of cards is a meld, e.g. the
+// decompositions, their score or the table, asks `validate_meld`.
+use core::fmt;
+
+use arrayvec::ArrayVec;
+
+use crate::*;
+
+/// Largest number of cards in a set, one per regular suit.
+pub const MAX_SET_SIZE: usize = 4;
+
+/// Smallest total value of the melds a player opens with.
+pub const MIN_OPENING_POINTS: u32 = 51;
+
+/// Type of a valid meld.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeldKind {
+    /// Cards of the same number in distinct suits, e.g. 7c 7d 7h.
+    Set { n: Value, jokers: usize },
+    /// Consecutive cards of a single suit from `lo` to `hi`, e.g. 5c 6c 7c.
+    /// `jokers` are the numbers the jokers stand in for.
+    Run {
+        suit: Suit,
+        lo: Value,
+        hi: Value,
+        jokers: ArrayVec<Value, MAX_HAND_SIZE>,
+    },
+}
+
+/// Reason why a group of cards isn't a meld.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeldRejection {
+    /// Fewer than three cards.
+    TooShort(usize),
+    /// A set of more than four cards, or a run longer than a suit.
+    TooLong(usize),
+    /// No regular card that determines the meld.
+    OnlyJokers,
+    /// A set with two cards of the same suit.
+    DuplicateSuit(Suit),
+    /// A run that contains a card twice.
+    DuplicateCard(Card),
+    /// Cards of different suits and numbers.
+    MixedSuits,
+    /// A run with more gaps than jokers to fill them.
+    NotConsecutive,
+}
+
+/// Checks whether the cards, given in any order, form a meld. Jokers
+/// complete a set, or fill the gaps of a run first and then extend it
+/// upwards. A single regular card with jokers is a set, as long as it
+/// fits into one.
+pub fn validate_meld<'a>(
+    cards: impl IntoIterator<Item = &'a Card>,
+) -> Result<MeldKind, MeldRejection> {
+    let cards: Vec<Card> = cards.into_iter().copied().collect();
+    if cards.len() < 3 {
+        return Err(MeldRejection::TooShort(cards.len()));
+    }
+    let mut real: Vec<Card> = cards
+        .iter()
+        .copied()
+        .filter(|c| c.suit != Suit::Joker)
+        .collect();
+    real.sort_unstable();
+    let jokers = cards.len() - real.len();
+    let first = *real.first().ok_or(MeldRejection::OnlyJokers)?;
+
+    let same_number = real.iter().all(|c| c.n == first.n);
+    if same_number && (real.len() > 1 || cards.len() <= MAX_SET_SIZE) {
+        if cards.len() > MAX_SET_SIZE {
+            return Err(MeldRejection::TooLong(cards.len()));
+        }
+        if let Some(w) = real.windows(2).find(|w| w[0].suit == w[1].suit) {
+            return Err(MeldRejection::DuplicateSuit(w[0].suit));
+        }
+        return Ok(MeldKind::Set { n: first.n, jokers });
+    }
+
+    if real.iter().any(|c| c.suit != first.suit) {
+        return Err(MeldRejection::MixedSuits);
+    }
+    if let Some(w) = real.windows(2).find(|w| w[0] == w[1]) {
+        return Err(MeldRejection::DuplicateCard(w[0]));
+    }
+    if cards.len() > MAX_CARD_VAL as usize {
+        return Err(MeldRejection::TooLong(cards.len()));
+    }
+    let (mut lo, mut hi) = (first.n.to_int(), real[real.len() - 1].n.to_int());
+    let mut slots: ArrayVec<Value, MAX_HAND_SIZE> = (lo..=hi)
+        .filter(|&n| !real.iter().any(|c| c.n == n))
+        .map(Value::new)
+        .collect();
+    if slots.len() > jokers {
+        return Err(MeldRejection::NotConsecutive);
+    }
+    while slots.len() < jokers {
+        if hi < MAX_CARD_VAL {
+            hi += 1;
+            slots.push(Value::new(hi));
+        } else {
+            lo -= 1;
+            slots.push(Value::new(lo));
+        }
+    }
+    Ok(MeldKind::Run {
+        suit: first.suit,
+        lo: Value::new(lo),
+        hi: Value::new(hi),
+        jokers: slots,
+    })
+}
+
+/// Value of a meld towards opening. Jokers are worth the card they stand
+/// in for, and an Ace counts as one at the start of a run.
+pub fn meld_points<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Result<u32, MeldRejection> {
+    let cards: Vec<Card> = cards.into_iter().copied().collect();
+    let points = |n: u32| match n {
+        1 => 10,
+        n => n.min(10),
+    };
+    Ok(match validate_meld(cards.iter())? {
+        MeldKind::Set { n, .. } => cards.len() as u32 * points(n.to_int()),
+        MeldKind::Run { lo, hi, .. } => (lo.to_int()..=hi.to_int())
+            .map(|n| if n == 1 { 1 } else { points(n) })
+            .sum(),
+    })
+}
+
+impl MeldKind {
+    /// Number of jokers in the meld.
+    pub fn jokers(&self) -> usize {
+        match self {
+            MeldKind::Set { jokers, .. } => *jokers,
+            MeldKind::Run { jokers, .. } => jokers.len(),
+        }
+    }
+}
+
+impl fmt::Display for MeldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeldKind::Set { n, .. } => write!(f, "set of {}", n)?,
+            MeldKind::Run { suit, lo, hi, .. } => write!(f, "run {}{}-{}{}", lo, suit, hi, suit)?,
+        }
+        match self.jokers() {
+            0 => Ok(()),
+            1 => write!(f, " with a joker"),
+            n => write!(f, " with {} jokers", n),
+        }
+    }
+}
+
+impl fmt::Display for MeldRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeldRejection::TooShort(n) => write!(f, "{} cards are too few for a meld", n),
+            MeldRejection::TooLong(n) => write!(f, "{} cards are too many for a meld", n),
+            MeldRejection::OnlyJokers => write!(f, "a meld needs at least one regular card"),
+            MeldRejection::DuplicateSuit(s) => {
+                write!(f, "a set can't contain two cards of suit {}", s)
+            }
+            MeldRejection::DuplicateCard(c) => write!(f, "a run can't contain {} twice", c),
+            MeldRejection::MixedSuits => write!(f, "a run must be of a single suit"),
+            MeldRejection::NotConsecutive => write!(f, "cards aren't consecutive"),
+        }
+    }
+}
+
+#[cfg(test)]
+fn check(s: &str) -> Result<MeldKind, MeldRejection> {
+    validate_meld(Hand::parse(s).unwrap().iter())
+}
+
+#[test]
+fn valid_melds() {
+    assert_eq!(check("7c 7d 7h").unwrap().to_string(), "set of 7");
+    assert_eq!(
+        check("7h X 7c 7s").unwrap().to_string(),
+        "set of 7 with a joker"
+    );
+    assert_eq!(check("6c 5c 7c").unwrap().to_string(), "run 5c-7c");
+    assert_eq!(check("Jh Qh Kh").unwrap().to_string(), "run Jh-Kh");
+
+    // the joker fills the gap first, then extends the run
+    let gap = check("5c X 7c").unwrap();
+    assert_eq!(gap.to_string(), "run 5c-7c with a joker");
+    let top = check("Qs Ks X").unwrap();
+    assert_eq!(top.to_string(), "run Js-Ks with a joker");
+    assert_eq!(
+        check("5c X X X X").unwrap().to_string(),
+        "run 5c-9c with 4 jokers"
+    );
+}
+
+#[test]
+fn opening_points() {
+    let points = |s: &str| meld_points(Hand::parse(s).unwrap().iter());
+    assert_eq!(points("Ac 2c 3c"), Ok(6));
+    assert_eq!(points("Ah As X"), Ok(30));
+    assert_eq!(points("Qd X Ad"), Err(MeldRejection::NotConsecutive));
+    assert_eq!(points("Jd X Kd"), Ok(30));
+    assert_eq!(points("9h 10h X"), Ok(29));
+}
+
+#[test]
+fn rejected_melds() {
+    use MeldRejection::*;
+    assert_eq!(check("7c 7d"), Err(TooShort(2)));
+    assert_eq!(check("X X X"), Err(OnlyJokers));
+    assert_eq!(check("7c 7d 7h 7s X"), Err(TooLong(5)));
+    assert_eq!(check("7h 7d 7h"), Err(DuplicateSuit(Suit::Hearts)));
+    assert_eq!(
+        check("5c 6c 6c 7c"),
+        Err(DuplicateCard(Card::parse("6c").unwrap()))
+    );
+    assert_eq!(check("5c 6d 7c"), Err(MixedSuits));
+    assert_eq!(check("5c 8c X"), Err(NotConsecutive));
+}
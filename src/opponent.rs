@@ -0,0 +1,204 @@
+// This module contains a Bayesian model of an opponent's hand. It starts
+// from the pool of unseen cards and updates per-card likelihoods whenever
+// the opponent reveals information by picking up, refusing or discarding
+// a card.
+use crate::*;
+
+/// Describes how an opponent is assumed to act. Each factor is the
+/// likelihood ratio applied to all cards that could form a meld with the
+/// observed card.
+#[derive(Copy, Clone, Debug)]
+pub struct BehaviorModel {
+    /// Applied when the opponent picks up a card from the discard pile.
+    pub pickup: f64,
+    /// Applied when the opponent draws from the stock instead of taking
+    /// the top of the discard pile.
+    pub refuse: f64,
+    /// Applied when the opponent discards a card.
+    pub discard: f64,
+}
+
+impl Default for BehaviorModel {
+    fn default() -> Self {
+        BehaviorModel {
+            pickup: 3.0,
+            refuse: 0.7,
+            discard: 0.5,
+        }
+    }
+}
+
+/// Probability distribution over the hand of a single opponent.
+#[derive(Clone, Debug)]
+pub struct OpponentModel {
+    behavior: BehaviorModel,
+    /// Cards that are unseen from our point of view.
+    pool: Deck,
+    /// Cards the opponent is known to hold, e.g. after a pickup.
+    known: Vec<Card>,
+    /// Number of cards in the opponent's hand.
+    hand_size: u32,
+    /// Relative likelihood of each unique card to be in the hand.
+    weights: [f64; UNIQUE_CARDS],
+}
+
+impl OpponentModel {
+    /// Creates a model for an opponent holding `hand_size` cards drawn
+    /// uniformly from the given pool of unseen cards.
+    pub fn new(pool: &Deck, hand_size: u32, behavior: BehaviorModel) -> Self {
+        OpponentModel {
+            behavior,
+            pool: pool.clone(),
+            known: Vec::new(),
+            hand_size,
+            weights: [1.0; UNIQUE_CARDS],
+        }
+    }
+
+    pub fn hand_size(&self) -> u32 {
+        self.hand_size
+    }
+
+    /// The opponent drew an unknown card from the stock instead of taking
+    /// the given top card of the discard pile.
+    pub fn observe_refusal(&mut self, top: Option<Card>) {
+        if let Some(c) = top {
+            self.scale_related(c, self.behavior.refuse);
+        }
+        self.hand_size += 1;
+    }
+
+    /// The opponent took the given card from the discard pile.
+    pub fn observe_pickup(&mut self, c: Card) {
+        self.scale_related(c, self.behavior.pickup);
+        self.known.push(c);
+        self.hand_size += 1;
+    }
+
+    /// The opponent discarded the given card.
+    pub fn observe_discard(&mut self, c: Card) {
+        self.scale_related(c, self.behavior.discard);
+        self.remove(c);
+    }
+
+    /// The opponent laid the given card onto the table.
+    pub fn observe_meld(&mut self, c: Card) {
+        self.remove(c);
+    }
+
+    /// A card has been revealed somewhere else, e.g. in our own draw, so
+    /// it can't be in the opponent's hand.
+    pub fn observe_seen(&mut self, c: Card) {
+        // the card may have been removed from the pool already
+        let _ = self.pool.remove(c);
+    }
+
+    /// Expected number of copies of the given card in the opponent's hand.
+    pub fn expected_copies(&self, c: Card) -> f64 {
+        let known = self.known.iter().filter(|&&k| k == c).count() as f64;
+        known + self.per_copy(c) * self.pool[c] as f64
+    }
+
+    /// Probability that the opponent holds at least one copy of the card.
+    pub fn p_holds(&self, c: Card) -> f64 {
+        if self.known.contains(&c) {
+            return 1.0;
+        }
+        1.0 - (1.0 - self.per_copy(c)).powi(self.pool[c] as i32)
+    }
+
+    /// Probability that the next card drawn from the stock is the given
+    /// card, given that the opponent's hand is not part of the stock.
+    /// Replaces the uniform `Deck::odds_to_draw`.
+    pub fn odds_to_draw(&self, c: Card) -> f64 {
+        let stock = self.pool.remaining() as f64 - self.unknown() as f64;
+        if stock <= 0.0 {
+            return 0.0;
+        }
+        let left = self.pool[c] as f64 - self.per_copy(c) * self.pool[c] as f64;
+        (left / stock).max(0.0)
+    }
+
+    /// Number of cards in the hand that are not known.
+    fn unknown(&self) -> u32 {
+        self.hand_size.saturating_sub(self.known.len() as u32)
+    }
+
+    /// Probability that a single unseen copy of the card is in the hand.
+    fn per_copy(&self, c: Card) -> f64 {
+        let mass: f64 = self
+            .pool
+            .iter()
+            .map(|(x, n)| self.weights[x.to_index()] * n as f64)
+            .sum();
+        if mass == 0.0 {
+            return 0.0;
+        }
+        (self.unknown() as f64 * self.weights[c.to_index()] / mass).min(1.0)
+    }
+
+    fn remove(&mut self, c: Card) {
+        match self.known.iter().position(|&k| k == c) {
+            Some(i) => {
+                self.known.swap_remove(i);
+            }
+            None => {
+                let _ = self.pool.remove(c);
+            }
+        }
+        self.hand_size = self.hand_size.saturating_sub(1);
+    }
+
+    fn scale_related(&mut self, c: Card, factor: f64) {
+        for r in related_cards(c) {
+            self.weights[r.to_index()] *= factor;
+        }
+    }
+}
+
+/// Returns all cards that can form a meld together with the given card,
+/// i.e. the same value in other suits and its neighbours in the same suit.
+pub fn related_cards(c: Card) -> Vec<Card> {
+    let mut v = Vec::new();
+    if c.suit == Suit::Joker {
+        return v;
+    }
+    for &suit in Suit::standard() {
+        if suit != c.suit {
+            v.push(Card { n: c.n, suit });
+        }
+    }
+    let prev = c.prev();
+    let next = c.next();
+    let second_prev = prev.and_then(|p| p.prev());
+    let second_next = next.and_then(|n| n.next());
+    v.extend([prev, next, second_prev, second_next].into_iter().flatten());
+    v
+}
+
+#[test]
+fn pickup_raises_neighbours() {
+    let h = Hand::parse("Ac 2c 3c 4c 5c 6c 7c").unwrap();
+    let pool = Deck::from_known(&h, &[], &[]).unwrap();
+    let mut m = OpponentModel::new(&pool, 14, BehaviorModel::default());
+    let eight = Card::parse("8h").unwrap();
+    let prior = m.p_holds(eight);
+    let prior_odds = m.odds_to_draw(eight);
+
+    m.observe_pickup(Card::parse("7h").unwrap());
+    assert_eq!(m.p_holds(Card::parse("7h").unwrap()), 1.0);
+    assert!(m.p_holds(eight) > prior);
+    assert!(m.odds_to_draw(eight) < prior_odds);
+}
+
+#[test]
+fn discard_lowers_neighbours() {
+    let pool = Deck::new();
+    let mut m = OpponentModel::new(&pool, 14, BehaviorModel::default());
+    let ten = Card::parse("10s").unwrap();
+    let prior = m.p_holds(ten);
+
+    m.observe_discard(Card::parse("Js").unwrap());
+    assert!(m.p_holds(ten) < prior);
+    assert_eq!(m.hand_size(), 13);
+}
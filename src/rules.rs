@@ -0,0 +1,170 @@
+// This module contains the rules of a match that vary between tables, and
+// the moves onto the table a player can make under them. The moves are made
+// between drawing and discarding.
+use crate::*;
+
+/// Smallest number of players at a table.
+pub const MIN_PLAYERS: usize = 2;
+
+/// Largest number of players at a table.
+pub const MAX_PLAYERS: usize = 6;
+
+/// Rules that vary between tables.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RuleSet {
+    /// Number of cards every player is dealt. The first player gets one
+    /// card more and starts by discarding.
+    pub deal_size: usize,
+    /// Smallest total value of the melds a player opens with.
+    pub opening_points: u32,
+    /// Jokers can only be taken from the table after opening.
+    pub exchange_after_opening: bool,
+    /// A joker taken from the table must be part of a new meld from the
+    /// hand in the same turn.
+    pub use_joker_immediately: bool,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            deal_size: DEAL_SIZE,
+            opening_points: MIN_OPENING_POINTS,
+            exchange_after_opening: true,
+            use_joker_immediately: true,
+        }
+    }
+}
+
+/// A move onto the table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Move {
+    /// Adds the card to the meld with the given index.
+    LayOff { meld: usize, card: Card },
+    /// Puts the card in place of a joker of the meld with the given index,
+    /// and takes the joker into the hand.
+    ExchangeJoker { meld: usize, card: Card },
+}
+
+/// All moves onto the table that can be made with the given hand. Only
+/// players that have opened can lay off.
+pub fn table_moves(h: &Hand, table: &Table, opened: bool, rules: &RuleSet) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for (i, &card) in h.iter().enumerate() {
+        if h[..i].contains(&card) {
+            continue;
+        }
+        if opened {
+            for meld in 0..table.melds.len() {
+                if table.meld_accepts(meld, card) {
+                    moves.push(Move::LayOff { meld, card });
+                }
+            }
+        }
+        if opened || !rules.exchange_after_opening {
+            let mut rest = h.clone();
+            rest.remove(i);
+            if rules.use_joker_immediately && !joker_fits(&rest) {
+                continue;
+            }
+            for meld in table.joker_exchanges(card) {
+                moves.push(Move::ExchangeJoker { meld, card });
+            }
+        }
+    }
+    moves
+}
+
+/// Makes the move, taking the card from the hand. Fails if the move isn't
+/// one of the `table_moves` under the given rules. The hand stays sorted.
+pub fn apply_move(
+    h: &mut Hand,
+    table: &mut Table,
+    opened: bool,
+    rules: &RuleSet,
+    mv: Move,
+) -> Result<(), Error> {
+    let card = match mv {
+        Move::LayOff { card, .. } | Move::ExchangeJoker { card, .. } => card,
+    };
+    let i = h
+        .iter()
+        .position(|&c| c == card)
+        .ok_or(Error::CardNotContained(card))?;
+    if !table_moves(h, table, opened, rules).contains(&mv) {
+        return Err(Error::IllegalMove("the rules don't allow the move"));
+    }
+    match mv {
+        Move::LayOff { meld, .. } => {
+            table.lay_off_onto(meld, card);
+            h.remove(i);
+        }
+        Move::ExchangeJoker { meld, .. } => {
+            let joker = table.exchange_joker(meld, card)?;
+            h[i] = joker;
+            h.sort_unstable();
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if a joker forms a meld with two cards of the hand.
+fn joker_fits(h: &Hand) -> bool {
+    (0..h.len())
+        .any(|i| (i + 1..h.len()).any(|j| validate_meld([h[i], h[j], JOKER_CARD].iter()).is_ok()))
+}
+
+#[test]
+fn exchange_rules() {
+    let table = Table {
+        melds: vec![Hand::parse("5c X 7c").unwrap()],
+    };
+    let h = Hand::parse_sorted("6c 9h 9s").unwrap();
+    let exchange = Move::ExchangeJoker {
+        meld: 0,
+        card: Card::parse("6c").unwrap(),
+    };
+    let rules = RuleSet::default();
+    assert!(!table_moves(&h, &table, false, &rules).contains(&exchange));
+    assert!(table_moves(&h, &table, true, &rules).contains(&exchange));
+
+    // without 9h 9s the joker can't be used right away
+    let alone = Hand::parse_sorted("6c 2d").unwrap();
+    assert!(!table_moves(&alone, &table, true, &rules).contains(&exchange));
+    let relaxed = RuleSet {
+        exchange_after_opening: false,
+        use_joker_immediately: false,
+        ..rules
+    };
+    assert!(table_moves(&alone, &table, false, &relaxed).contains(&exchange));
+}
+
+#[test]
+fn apply_moves() {
+    let mut table = Table {
+        melds: vec![Hand::parse("5c X 7c").unwrap()],
+    };
+    let mut h = Hand::parse_sorted("6c 8c 9h 9s").unwrap();
+    let exchange = Move::ExchangeJoker {
+        meld: 0,
+        card: Card::parse("6c").unwrap(),
+    };
+    let extend = Move::LayOff {
+        meld: 0,
+        card: Card::parse("8c").unwrap(),
+    };
+    let rules = RuleSet::default();
+    let moves = table_moves(&h, &table, true, &rules);
+    assert!(moves.contains(&exchange) && moves.contains(&extend));
+    // jokers can only be taken after opening
+    assert!(apply_move(&mut h, &mut table, false, &rules, exchange).is_err());
+    apply_move(&mut h, &mut table, true, &rules, exchange).unwrap();
+    apply_move(&mut h, &mut table, true, &rules, extend).unwrap();
+    assert_eq!(h.display().to_string(), "9h 9s X");
+    assert_eq!(table.melds[0].display().to_string(), "5c 6c 7c 8c");
+
+    let lay_off = Move::LayOff {
+        meld: 0,
+        card: Card::parse("9h").unwrap(),
+    };
+    assert!(apply_move(&mut h, &mut table, true, &rules, lay_off).is_err());
+}
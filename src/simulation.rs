@@ -0,0 +1,270 @@
+// This module contains a harness that plays many seeded rounds between a
+// fixed set of bots and collects per-game statistics, in order to measure
+// whether a change to the evaluation actually wins more games. Every deal
+// is played once from every seat, so no bot profits from a lucky seat.
+use core::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::*;
+
+/// Configuration of a simulation run.
+#[derive(Copy, Clone, Debug)]
+pub struct SimulationConfig {
+    /// Total number of games, rounded up to a multiple of the number of
+    /// bots, so that every deal is played from every seat.
+    pub games: u32,
+    /// Turn limit of a single round.
+    pub max_turns: u32,
+    /// Seed of the first deal. Every further deal increments it.
+    pub seed: u64,
+    /// Rules every round is played by.
+    pub rules: RuleSet,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            games: 100,
+            max_turns: 100,
+            seed: 0,
+            rules: RuleSet::default(),
+        }
+    }
+}
+
+/// Statistics of a single game. All vectors are indexed by bot, not by
+/// seat.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameStats {
+    pub game: u32,
+    /// Seed of the deal.
+    pub seed: u64,
+    /// Bot that was dealt the additional card and started.
+    pub first: usize,
+    pub winner: Option<usize>,
+    pub turns: u32,
+    pub points: Vec<u32>,
+    pub opening_turns: Vec<Option<u32>>,
+}
+
+/// Sample mean with the half-width of its 95% confidence interval.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Estimate {
+    pub mean: f64,
+    pub ci95: f64,
+    pub samples: u32,
+}
+
+/// Aggregate statistics of a simulation run, see `summarize`.
+#[derive(Clone, Debug)]
+pub struct SimulationSummary {
+    /// Number of games asked for in the config.
+    pub requested: u32,
+    /// Number of games actually played, see `SimulationConfig::games`.
+    pub games: u32,
+    pub draws: u32,
+    pub turns: Estimate,
+    /// Per bot, the fraction of games won.
+    pub win_rate: Vec<Estimate>,
+    /// Per bot, the penalty points at the end of a game.
+    pub points: Vec<Estimate>,
+    /// Per bot, the turn its hand was first worth opening, over the games
+    /// in which that happened.
+    pub opening_turn: Vec<Estimate>,
+}
+
+/// Plays `cfg.games` rounds between the given bots. Game `i` uses the deal
+/// of game `i - i % bots.len()` with the seats rotated by `i % bots.len()`.
+pub fn simulate(bots: &[EvalParams], cfg: &SimulationConfig) -> Result<Vec<GameStats>, Error> {
+    let n = bots.len();
+    if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&n) {
+        return Err(Error::PlayerCount(n));
+    }
+    let deals = (cfg.games as usize).div_ceil(n);
+    let mut games = Vec::with_capacity(deals * n);
+    for i in 0..deals * n {
+        let (deal, rotation) = (i / n, i % n);
+        let seed = cfg.seed.wrapping_add(deal as u64);
+        // the bot in seat `s`
+        let bot = |s: usize| (s + rotation) % n;
+        let seated: Vec<EvalParams> = (0..n).map(|s| bots[bot(s)]).collect();
+        let result = play_round(&seated, &cfg.rules, seed, cfg.max_turns)?;
+
+        let mut points = vec![0; n];
+        let mut opening_turns = vec![None; n];
+        for s in 0..n {
+            points[bot(s)] = result.points[s];
+            opening_turns[bot(s)] = result.opening_turns[s];
+        }
+        games.push(GameStats {
+            game: i as u32,
+            seed,
+            first: bot(0),
+            winner: result.winner.map(bot),
+            turns: result.turns,
+            points,
+            opening_turns,
+        });
+    }
+    Ok(games)
+}
+
+/// Writes one line per game in CSV format, with a header. Games without a
+/// winner and bots that never could open have empty fields.
+pub fn write_csv(games: &[GameStats], path: &Path) -> Result<(), Error> {
+    let io = |e: std::io::Error| Error::IoError(e.to_string());
+    let mut w = BufWriter::new(File::create(path).map_err(io)?);
+    let bots = games.first().map_or(0, |g| g.points.len());
+    write!(w, "game,seed,first,winner,turns").map_err(io)?;
+    for b in 0..bots {
+        write!(w, ",points_{}", b).map_err(io)?;
+    }
+    for b in 0..bots {
+        write!(w, ",opening_turn_{}", b).map_err(io)?;
+    }
+    writeln!(w).map_err(io)?;
+
+    let optional = |x: Option<u32>| x.map_or(String::new(), |x| x.to_string());
+    for g in games {
+        let winner = optional(g.winner.map(|w| w as u32));
+        write!(
+            w,
+            "{},{},{},{},{}",
+            g.game, g.seed, g.first, winner, g.turns
+        )
+        .map_err(io)?;
+        for p in g.points.iter() {
+            write!(w, ",{}", p).map_err(io)?;
+        }
+        for &t in g.opening_turns.iter() {
+            write!(w, ",{}", optional(t)).map_err(io)?;
+        }
+        writeln!(w).map_err(io)?;
+    }
+    w.flush().map_err(io)
+}
+
+/// Aggregates the statistics of all games played with the given config.
+pub fn summarize(cfg: &SimulationConfig, games: &[GameStats]) -> SimulationSummary {
+    let bots = games.first().map_or(0, |g| g.points.len());
+    let per_bot = |f: &dyn Fn(&GameStats, usize) -> Option<f64>| -> Vec<Estimate> {
+        (0..bots)
+            .map(|b| Estimate::from_samples(games.iter().filter_map(|g| f(g, b))))
+            .collect()
+    };
+    SimulationSummary {
+        requested: cfg.games,
+        games: games.len() as u32,
+        draws: games.iter().filter(|g| g.winner.is_none()).count() as u32,
+        turns: Estimate::from_samples(games.iter().map(|g| g.turns as f64)),
+        win_rate: per_bot(&|g, b| Some((g.winner == Some(b)) as u32 as f64)),
+        points: per_bot(&|g, b| Some(g.points[b] as f64)),
+        opening_turn: per_bot(&|g, b| g.opening_turns[b].map(|t| t as f64)),
+    }
+}
+
+impl Estimate {
+    /// Normal approximation of the confidence interval of the mean.
+    pub fn from_samples(xs: impl Iterator<Item = f64>) -> Estimate {
+        let xs: Vec<f64> = xs.collect();
+        if xs.is_empty() {
+            return Estimate::default();
+        }
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let variance = match xs.len() {
+            1 => 0.0,
+            _ => xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0),
+        };
+        Estimate {
+            mean,
+            ci95: 1.96 * (variance / n).sqrt(),
+            samples: xs.len() as u32,
+        }
+    }
+
+    /// Returns true if the confidence intervals don't overlap.
+    pub fn differs_from(&self, other: &Estimate) -> bool {
+        (self.mean - other.mean).abs() > self.ci95 + other.ci95
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} ± {:.3}", self.mean, self.ci95)
+    }
+}
+
+impl fmt::Display for SimulationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "games: {}", self.games)?;
+        if self.games != self.requested {
+            write!(
+                f,
+                " ({} requested, rounded up to rotate all seats)",
+                self.requested
+            )?;
+        }
+        writeln!(f, ", draws: {}", self.draws)?;
+        writeln!(f, "turns: {}", self.turns)?;
+        for b in 0..self.win_rate.len() {
+            writeln!(
+                f,
+                "bot {}: wins {}, points {}, opening turn {} ({} games)",
+                b,
+                self.win_rate[b],
+                self.points[b],
+                self.opening_turn[b],
+                self.opening_turn[b].samples
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn simulation_rotates_seats() {
+    let cfg = SimulationConfig {
+        games: 3,
+        max_turns: 30,
+        seed: 7,
+        ..SimulationConfig::default()
+    };
+    let bots = [EvalParams::default(), EvalParams::default()];
+    let games = simulate(&bots, &cfg).unwrap();
+    assert_eq!(games.len(), 4);
+    assert_eq!(games[0].seed, games[1].seed);
+    assert_eq!((games[0].first, games[1].first), (0, 1));
+    // identical bots play the same game from swapped seats
+    assert_eq!(games[0].turns, games[1].turns);
+    assert_eq!(games[0].points[0], games[1].points[1]);
+    assert_eq!(games, simulate(&bots, &cfg).unwrap());
+    assert!(simulate(&bots[..1], &cfg).is_err());
+
+    let summary = summarize(&cfg, &games);
+    assert_eq!((summary.requested, summary.games), (3, 4));
+    assert!(summary.to_string().starts_with("games: 4 (3 requested"));
+    assert_eq!(summary.win_rate[0].mean, summary.win_rate[1].mean);
+    assert!(!summary.points[0].differs_from(&summary.points[1]));
+
+    let path = std::env::temp_dir().join(format!("remi-simulation-{}.csv", std::process::id()));
+    write_csv(&games, &path).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(csv.lines().count(), 5);
+    assert!(csv.starts_with("game,seed,first,winner,turns,points_0,points_1,opening_turn_0,"));
+}
+
+#[test]
+fn confidence_interval() {
+    let e = Estimate::from_samples([1.0, 0.0, 1.0, 0.0].into_iter());
+    assert_eq!(e.mean, 0.5);
+    assert_eq!(e.samples, 4);
+    assert!((e.ci95 - 1.96 * (1.0f64 / 12.0).sqrt()).abs() < 1e-12);
+    assert_eq!(
+        Estimate::from_samples(std::iter::empty()),
+        Estimate::default()
+    );
+}
@@ -0,0 +1,172 @@
+// This module contains an automatic tuner for the evaluation weights. It
+// runs a simple (1+1) evolutionary loop: a perturbed challenger plays a
+// series of seeded self-play rounds against the current champion and
+// replaces it if it wins the majority. Progress is stored on disk after
+// every iteration, so an interrupted run can be resumed.
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::*;
+
+/// Configuration of a tuning run.
+#[derive(Copy, Clone, Debug)]
+pub struct TuneConfig {
+    /// Total number of iterations, including those of earlier runs.
+    pub iterations: u32,
+    /// Rounds played between challenger and champion per iteration.
+    pub rounds: u32,
+    /// Relative size of the random perturbation of every weight.
+    pub step: f64,
+    /// Turn limit of a single round.
+    pub max_turns: u32,
+    pub seed: u64,
+    /// Rules every round is played by.
+    pub rules: RuleSet,
+}
+
+impl Default for TuneConfig {
+    fn default() -> Self {
+        TuneConfig {
+            iterations: 100,
+            rounds: 20,
+            step: 0.2,
+            max_turns: 100,
+            seed: 0,
+            rules: RuleSet::default(),
+        }
+    }
+}
+
+/// Match statistics of a single tuning iteration.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IterationStats {
+    pub iteration: u32,
+    pub challenger_wins: u32,
+    pub champion_wins: u32,
+    pub draws: u32,
+    pub accepted: bool,
+}
+
+/// Tunes the evaluation weights by self-play.
+///
+/// The best parameter set is written to `params_path` and one line of
+/// match statistics per iteration is appended to `log_path`. If both files
+/// exist, the run resumes from the stored champion and iteration count.
+pub fn tune(cfg: &TuneConfig, params_path: &Path, log_path: &Path) -> Result<EvalParams, Error> {
+    let (mut champion, start) = match (params_path.exists(), log_path.exists()) {
+        (true, true) => {
+            let log =
+                std::fs::read_to_string(log_path).map_err(|e| Error::IoError(e.to_string()))?;
+            let done = log.lines().filter(|l| !l.starts_with('#')).count() as u32;
+            (EvalParams::from_file(params_path)?, done)
+        }
+        _ => {
+            std::fs::write(
+                log_path,
+                "# iteration challenger_wins champion_wins draws accepted\n",
+            )
+            .map_err(|e| Error::IoError(e.to_string()))?;
+            (EvalParams::default(), 0)
+        }
+    };
+
+    for iteration in start..cfg.iterations {
+        let (next, stats) = tune_iteration(&champion, cfg, iteration)?;
+        champion = next;
+        champion.to_file(params_path)?;
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(log_path)
+            .map_err(|e| Error::IoError(e.to_string()))?;
+        writeln!(
+            log,
+            "{} {} {} {} {}",
+            stats.iteration,
+            stats.challenger_wins,
+            stats.champion_wins,
+            stats.draws,
+            stats.accepted
+        )
+        .map_err(|e| Error::IoError(e.to_string()))?;
+    }
+    Ok(champion)
+}
+
+/// Plays a single iteration and returns the new champion. All randomness
+/// is derived from the seed and the iteration, so iterations are
+/// reproducible independent of earlier runs.
+pub fn tune_iteration(
+    champion: &EvalParams,
+    cfg: &TuneConfig,
+    iteration: u32,
+) -> Result<(EvalParams, IterationStats), Error> {
+    let seed = cfg.seed ^ (iteration as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let rng = fastrand::Rng::with_seed(seed);
+    let challenger = perturb(champion, cfg.step, &rng);
+
+    let mut stats = IterationStats {
+        iteration,
+        challenger_wins: 0,
+        champion_wins: 0,
+        draws: 0,
+        accepted: false,
+    };
+    for round in 0..cfg.rounds {
+        // both sides play every deal from both seats
+        let deal_seed = seed.wrapping_add((round / 2) as u64);
+        let seat = (round % 2) as usize;
+        let mut bots = [*champion, *champion];
+        bots[seat] = challenger;
+        match play_round(&bots, &cfg.rules, deal_seed, cfg.max_turns)?.winner {
+            Some(w) if w == seat => stats.challenger_wins += 1,
+            Some(_) => stats.champion_wins += 1,
+            None => stats.draws += 1,
+        }
+    }
+    stats.accepted = stats.challenger_wins > stats.champion_wins;
+    match stats.accepted {
+        true => Ok((challenger, stats)),
+        false => Ok((*champion, stats)),
+    }
+}
+
+/// Randomly perturbs every weight. Weights never become negative.
+fn perturb(params: &EvalParams, step: f64, rng: &fastrand::Rng) -> EvalParams {
+    let mut p = *params;
+    for w in p.fields() {
+        let scale = w.abs().max(0.05);
+        *w = (*w + step * scale * (rng.f64() * 2.0 - 1.0)).max(0.0);
+    }
+    p
+}
+
+#[test]
+fn tuning_is_resumable() {
+    let dir = std::env::temp_dir().join(format!("remi-tune-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let params_path = dir.join("params.txt");
+    let log_path = dir.join("log.txt");
+    let cfg = TuneConfig {
+        iterations: 1,
+        rounds: 2,
+        max_turns: 30,
+        ..TuneConfig::default()
+    };
+    tune(&cfg, &params_path, &log_path).unwrap();
+    let resumed = tune(
+        &TuneConfig {
+            iterations: 2,
+            ..cfg
+        },
+        &params_path,
+        &log_path,
+    )
+    .unwrap();
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert_eq!(log.lines().count(), 3);
+    assert!(log.lines().last().unwrap().starts_with("1 "));
+    assert_eq!(EvalParams::from_file(&params_path).unwrap(), resumed);
+    std::fs::remove_dir_all(&dir).unwrap();
+}